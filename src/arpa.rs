@@ -0,0 +1,285 @@
+// vim:nowrap:
+
+// [ARPA format](https://cmusphinx.github.io/wiki/arpaformat/)
+// [KenLM](https://kheafield.com/code/kenlm/structures/) expects `<s>`, `</s>` and `<unk>` unigrams.
+// `<s>` is never predicted and gets the conventional log10 probability of -99.
+
+use std::collections::HashMap;
+use std::io::{
+    BufRead,
+    Write,
+    self,
+};
+
+use crate::freq_of_freq::{
+    freq_of_freq,
+    good_turing,
+};
+use crate::ngrams::{
+    BOS,
+    EOS,
+};
+use crate::vocabulary::total;
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+pub const UNK: &str = "<unk>";
+
+/// log10 probability of `<s>`.
+pub const BOS_LOG_PROB: f64 = -99.0;
+
+
+
+/// An n-gram with its log10 probability and optional log10 backoff weight.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArpaEntry {
+    pub ngram: String,
+    pub log_prob: f64,
+    pub backoff: Option<f64>,
+}
+
+
+
+/// Write an ARPA model whose n-grams of order `i + 1` are `orders[i]`.
+pub fn write_arpa<W: Write>(mut out: W, orders: &[Vec<ArpaEntry>]) -> io::Result<()> {
+    writeln!(out)?;
+    writeln!(out, "\\data\\")?;
+    for (i, entries) in orders.iter().enumerate() {
+        writeln!(out, "ngram {}={}", i + 1, entries.len())?;
+    }
+
+    for (i, entries) in orders.iter().enumerate() {
+        writeln!(out)?;
+        writeln!(out, "\\{}-grams:", i + 1)?;
+        for entry in entries {
+            match entry.backoff {
+                Some(backoff) => writeln!(out, "{:.6}\t{}\t{:.6}", entry.log_prob, entry.ngram, backoff)?,
+                None => writeln!(out, "{:.6}\t{}", entry.log_prob, entry.ngram)?,
+            }
+        }
+    }
+
+    writeln!(out)?;
+    writeln!(out, "\\end\\")?;
+    out.flush()
+}
+
+
+
+/// Smoothing of a unigram model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Smoothing {
+    /// Add `k` to every count, `<unk>` included.
+    AddK(f64),
+    /// Good-Turing adjusted counts with Katz's cutoff, `<unk>` gets the singleton mass `N1 / N`.
+    GoodTuring,
+}
+
+
+
+/// Unigram model of a vocabulary in which `</s>` was seen `sentences` times.
+/// `<unk>` gets the mass reserved by the smoothing, or exactly `unk_mass`, plus its own share if
+/// the vocabulary already has `<unk>` and no `unk_mass` is given.
+/// Without sentences, `</s>` is unseen and gets the probability of `<unk>`.
+/// The entries are sorted by decreasing probability after `<unk>` and `<s>`.
+/// Fails when no mass is left for `<unk>`, e.g. with Good-Turing and no singletons.
+pub fn unigram_model(counts: &Counts, sentences: u32, smoothing: Smoothing, unk_mass: Option<f64>) -> Result<Vec<ArpaEntry>, String> {
+    let mut counts: Counts = counts
+        .iter()
+        .filter(|&(word, _)| word != BOS)
+        .filter(|&(word, _)| unk_mass.is_none() || word != UNK)
+        .map(|(word, &count)| (word.clone(), count))
+        .collect();
+    if sentences > 0 {
+        *counts.entry(EOS.to_owned()).or_insert(0u32) += sentences;
+    }
+
+    let (reserved, raw): (f64, Vec<(&String, f64)>) = match smoothing {
+        Smoothing::AddK(k) => {
+            let n = total(&counts) as f64;
+            let types = counts.len() as f64;
+            let reserved = k / (n + k * (types + 1.0));
+            (reserved, counts.iter().map(|(word, &count)| (word, count as f64 + k)).collect())
+        }
+        Smoothing::GoodTuring => {
+            let gt = good_turing(&freq_of_freq(&counts));
+            (gt.unseen_mass, counts.iter().map(|(word, count)| (word, gt.adjusted[count])).collect())
+        }
+    };
+    let reserved = unk_mass.unwrap_or(reserved);
+    if !(reserved > 0.0 && reserved < 1.0) {
+        return Err(format!("the smoothing reserves a mass of {} for <unk>, use --unk-mass", reserved));
+    }
+    let raw_total: f64 = raw.iter().map(|&(_, raw)| raw).sum();
+
+    let mut unk_prob = reserved;
+    let mut entries: Vec<ArpaEntry> = Vec::with_capacity(raw.len() + 2);
+    for (word, raw) in raw {
+        let prob = (1.0 - reserved) * raw / raw_total;
+        if word == UNK {
+            unk_prob += prob;
+        }
+        else {
+            entries.push(ArpaEntry {
+                ngram: word.clone(),
+                log_prob: prob.log10(),
+                backoff: None,
+            });
+        }
+    }
+    if !counts.contains_key(EOS) {
+        entries.push(ArpaEntry {
+            ngram: EOS.to_owned(),
+            log_prob: unk_prob.log10(),
+            backoff: None,
+        });
+    }
+    entries.sort_unstable_by(|a, b| b.log_prob.total_cmp(&a.log_prob).then(a.ngram.cmp(&b.ngram)));
+
+    let special = [
+        ArpaEntry {
+            ngram: UNK.to_owned(),
+            log_prob: unk_prob.log10(),
+            backoff: None,
+        },
+        ArpaEntry {
+            ngram: BOS.to_owned(),
+            log_prob: BOS_LOG_PROB,
+            backoff: None,
+        },
+    ];
+    Ok(special.into_iter().chain(entries).collect())
+}
+
+
+
+/// A backoff model for scoring, `orders[i]` maps the n-grams of order `i + 1` to their log10
+/// probability and backoff.
+#[derive(Debug, Clone, Default)]
+pub struct BackoffModel {
+    orders: Vec<HashMap<String, (f64, f64)>>,
+}
+
+
+
+impl BackoffModel {
+    pub fn new(orders: Vec<Vec<ArpaEntry>>) -> Self {
+        BackoffModel {
+            orders: orders
+                .into_iter()
+                .map(|entries| {
+                    entries
+                        .into_iter()
+                        .map(|entry| (entry.ngram, (entry.log_prob, entry.backoff.unwrap_or(0.0))))
+                        .collect()
+                })
+                .collect(),
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// log10 probability of `word` after `context`, whose most recent word is last.
+    /// Unknown words get the probability of `<unk>`, or -99 without `<unk>`.
+    pub fn log_prob<S: AsRef<str>>(&self, context: &[S], word: &str) -> f64 {
+        let n = context.len().min(self.order().saturating_sub(1));
+        let context: Vec<&str> = context[context.len() - n..].iter().map(AsRef::as_ref).collect();
+
+        let mut backoff = 0.0;
+        for k in (0..=n).rev() {
+            let history = context[n - k..].join(" ");
+            let ngram = if k == 0 { word.to_owned() } else { format!("{} {}", history, word) };
+            if let Some(&(log_prob, _)) = self.orders[k].get(&ngram) {
+                return backoff + log_prob;
+            }
+            if k > 0 {
+                backoff += self.orders[k - 1].get(&history).map_or(0.0, |&(_, backoff)| backoff);
+            }
+        }
+
+        backoff + self.orders[0].get(UNK).map_or(BOS_LOG_PROB, |&(log_prob, _)| log_prob)
+    }
+
+    /// log10 probability of a sentence between `<s>` and `</s>` and its number of predicted
+    /// words, `</s>` included.
+    pub fn sentence_log_prob<S: AsRef<str>>(&self, tokens: &[S]) -> (f64, usize) {
+        self.log_prob_after_bos(tokens, true)
+    }
+
+    /// log10 probability of the words of a sentence after `<s>` and their number, `</s>`
+    /// excluded.
+    pub fn words_log_prob<S: AsRef<str>>(&self, tokens: &[S]) -> (f64, usize) {
+        self.log_prob_after_bos(tokens, false)
+    }
+
+    fn log_prob_after_bos<S: AsRef<str>>(&self, tokens: &[S], eos: bool) -> (f64, usize) {
+        let mut words: Vec<&str> = Vec::with_capacity(tokens.len() + 2);
+        words.push(BOS);
+        words.extend(tokens.iter().map(AsRef::as_ref));
+        if eos {
+            words.push(EOS);
+        }
+
+        let log_prob = (1..words.len())
+            .map(|i| self.log_prob(&words[..i], words[i]))
+            .sum();
+
+        (log_prob, words.len() - 1)
+    }
+}
+
+
+
+/// Read an ARPA model.
+pub fn read_arpa(filename: &Option<String>) -> io::Result<BackoffModel> {
+    let invalid = |i: usize, message: String| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", i + 1, message));
+
+    let mut model = BackoffModel::default();
+    // Order of the current section, 0 before the first one.
+    let mut order = 0usize;
+    for (i, line) in try_get_reader(filename)?.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line == "\\data\\" || line.starts_with("ngram ") {
+            continue;
+        }
+        if line == "\\end\\" {
+            break;
+        }
+        if let Some(section) = line.strip_prefix('\\').and_then(|line| line.strip_suffix("-grams:")) {
+            order = section.parse().map_err(|_| invalid(i, format!("invalid section {:?}", line)))?;
+            if order != model.orders.len() + 1 {
+                return Err(invalid(i, format!("expected the {}-grams", model.orders.len() + 1)));
+            }
+            model.orders.push(HashMap::new());
+            continue;
+        }
+        if order == 0 {
+            return Err(invalid(i, "n-gram before the first section".to_owned()));
+        }
+
+        let mut columns = line.split('\t');
+        let (log_prob, ngram) = match (columns.next(), columns.next()) {
+            (Some(log_prob), Some(ngram)) => (log_prob, ngram),
+            _ => return Err(invalid(i, format!("expected `log_prob\\tngram`, got {:?}", line))),
+        };
+        let parse = |value: &str| value.trim().parse::<f64>().map_err(|e| invalid(i, format!("invalid number {:?}: {}", value, e)));
+        let log_prob = parse(log_prob)?;
+        let backoff = columns.next().map(parse).transpose()?.unwrap_or(0.0);
+        model.orders[order - 1].insert(ngram.split_whitespace().collect::<Vec<_>>().join(" "), (log_prob, backoff));
+    }
+
+    if model.orders.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "no n-grams"));
+    }
+
+    Ok(model)
+}
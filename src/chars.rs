@@ -0,0 +1,196 @@
+// vim:nowrap:
+
+// [Unicode Character Database](https://www.unicode.org/reports/tr44/)
+// [Grapheme clusters](https://www.unicode.org/reports/tr29/)
+// Characters are counted raw, before the tokenizer pipeline, to spot mojibake and foreign scripts.
+
+use std::collections::BTreeMap;
+use std::io::{
+    BufRead,
+    self,
+};
+
+use unicode_general_category::{
+    get_general_category,
+    GeneralCategory,
+};
+use unicode_script::{
+    Script,
+    UnicodeScript,
+};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    char_windows,
+    try_get_reader,
+    grapheme_windows,
+    Counts,
+};
+
+
+
+/// What makes a character.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CharUnit {
+    #[default]
+    CodePoint,
+    /// Extended grapheme cluster, e.g. a letter with its combining marks or an emoji sequence.
+    Grapheme,
+}
+
+
+
+impl CharUnit {
+    /// The characters of `text`.
+    pub fn split<'t>(&self, text: &'t str) -> Box<dyn Iterator<Item = &'t str> + 't> {
+        match self {
+            CharUnit::CodePoint => Box::new(text.char_indices().map(move |(i, c)| &text[i..i + c.len_utf8()])),
+            CharUnit::Grapheme => Box::new(text.graphemes(true)),
+        }
+    }
+
+    /// The overlapping sequences of `n` characters of `text`.
+    pub fn windows<'t>(&self, text: &'t str, n: usize) -> Box<dyn Iterator<Item = &'t str> + 't> {
+        match self {
+            CharUnit::CodePoint => Box::new(char_windows(text, n.max(1))),
+            CharUnit::Grapheme => Box::new(grapheme_windows(text, n.max(1))),
+        }
+    }
+}
+
+
+
+/// Suspicious characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Flag {
+    /// Control characters other than tab.
+    Control,
+    PrivateUse,
+    /// U+FFFD, usually left by decoding invalid UTF-8.
+    Replacement,
+}
+
+
+
+impl Flag {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Flag::Control => "control",
+            Flag::PrivateUse => "private_use",
+            Flag::Replacement => "replacement",
+        }
+    }
+
+    fn of(c: char) -> Option<Flag> {
+        match get_general_category(c) {
+            _ if c == char::REPLACEMENT_CHARACTER => Some(Flag::Replacement),
+            GeneralCategory::Control if c != '\t' => Some(Flag::Control),
+            GeneralCategory::PrivateUse => Some(Flag::PrivateUse),
+            _ => None,
+        }
+    }
+}
+
+
+
+/// Unicode properties of a character.
+/// Those of a grapheme cluster are the ones of its first code point except for its script, which
+/// is the first one that isn't Common or Inherited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CharInfo {
+    /// `U+XXXX` of each code point.
+    pub code_points: String,
+    /// Names of the code points joined by ` + `.
+    pub name: String,
+    /// General category abbreviation, e.g. Lu.
+    pub category: &'static str,
+    pub script: &'static str,
+    pub flags: Vec<Flag>,
+}
+
+
+
+impl CharInfo {
+    pub fn new(unit: &str) -> Self {
+        let code_points: Vec<String> = unit.chars().map(|c| format!("U+{:04X}", c as u32)).collect();
+        let names: Vec<String> = unit
+            .chars()
+            .map(|c| match unicode_names2::name(c) {
+                Some(name) => name.to_string(),
+                None if get_general_category(c) == GeneralCategory::Control => "<control>".to_owned(),
+                None => "<unnamed>".to_owned(),
+            })
+            .collect();
+        let script = unit
+            .chars()
+            .map(|c| c.script())
+            .find(|&script| script != Script::Common && script != Script::Inherited)
+            .or_else(|| unit.chars().next().map(|c| c.script()))
+            .unwrap_or(Script::Unknown);
+        let mut flags: Vec<Flag> = unit.chars().filter_map(Flag::of).collect();
+        flags.sort_unstable();
+        flags.dedup();
+
+        CharInfo {
+            code_points: code_points.join(" "),
+            name: names.join(" + "),
+            category: unit.chars().next().map_or("Cn", |c| get_general_category(c).abbreviation()),
+            script: script.full_name(),
+            flags,
+        }
+    }
+
+    /// The character itself, escaped when it is a control or format character.
+    pub fn display(unit: &str) -> String {
+        let invisible = unit
+            .chars()
+            .any(|c| matches!(get_general_category(c), GeneralCategory::Control | GeneralCategory::Format));
+        if invisible { unit.escape_debug().to_string() } else { unit.to_owned() }
+    }
+}
+
+
+
+/// Buffer - While - for-loop counting the characters of each line, without its line terminator.
+/// Invalid UTF-8 is decoded as U+FFFD so that it shows up as replacement characters.
+pub fn char_count(filename: &Option<String>, unit: CharUnit) -> io::Result<Counts> {
+    let mut counts = Counts::new();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line: Vec<u8> = Vec::with_capacity(1024);
+    while rdr.read_until(b'\n', &mut line)? > 0 {
+        let decoded = String::from_utf8_lossy(&line);
+        let text = decoded.strip_suffix('\n').unwrap_or(&decoded);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        for c in unit.split(text) {
+            *counts.entry(c.to_owned()).or_insert(0u32) += 1u32;
+        }
+        line.clear();
+    }
+
+    Ok(counts)
+}
+
+
+
+/// Number of characters per script and per flag.
+#[derive(Debug, Clone, Default)]
+pub struct CharSummary {
+    pub scripts: BTreeMap<&'static str, u64>,
+    pub flags: BTreeMap<Flag, u64>,
+}
+
+
+
+/// Summary of character counts.
+pub fn char_summary(counts: &Counts) -> CharSummary {
+    let mut summary = CharSummary::default();
+    for (unit, &count) in counts {
+        let info = CharInfo::new(unit);
+        *summary.scripts.entry(info.script).or_insert(0u64) += count as u64;
+        for flag in info.flags {
+            *summary.flags.entry(flag).or_insert(0u64) += count as u64;
+        }
+    }
+    summary
+}
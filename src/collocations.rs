@@ -0,0 +1,96 @@
+// vim:nowrap:
+
+// [Collocations](https://nlp.stanford.edu/fsnlp/promo/colloc.pdf) chapter 5 of Manning and Schütze.
+// [Normalized (Pointwise) Mutual Information in Collocation Extraction](https://svn.spraakdata.gu.se/repos/gerlof/pub/www/Docs/npmi-pfd.pdf)
+// A bigram `x y` forms a 2x2 contingency table:
+//               y            not y
+//   x           f(xy)        f(x) - f(xy)
+//   not x       f(y) - f(xy) N - f(x) - f(y) + f(xy)
+
+use crate::ngrams::NgramCounts;
+use crate::vocabulary::total;
+
+
+
+/// Association scores of a bigram.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Association {
+    /// Pointwise mutual information in bits.
+    pub pmi: f64,
+    /// PMI normalized to [-1, 1].
+    pub npmi: f64,
+    pub t_score: f64,
+    /// Dunning's log-likelihood G2.
+    pub log_likelihood: f64,
+}
+
+
+
+/// Log-likelihood G2 of a 2x2 contingency table.
+pub fn log_likelihood(k11: f64, k12: f64, k21: f64, k22: f64) -> f64 {
+    let n = k11 + k12 + k21 + k22;
+    let term = |k: f64, row: f64, column: f64| {
+        if k > 0.0 { k * (k * n / (row * column)).ln() } else { 0.0 }
+    };
+
+    2.0 * (term(k11, k11 + k12, k11 + k21)
+        + term(k12, k11 + k12, k12 + k22)
+        + term(k21, k21 + k22, k11 + k21)
+        + term(k22, k21 + k22, k12 + k22))
+}
+
+
+
+/// Association of a bigram seen `xy` times whose tokens are seen `x` and `y` times in `n` tokens.
+pub fn association(xy: u64, x: u64, y: u64, n: u64) -> Association {
+    let (xy, x, y, n) = (xy as f64, x as f64, y as f64, n as f64);
+
+    let pmi = (xy * n / (x * y)).log2();
+    let p_xy = xy / n;
+    let npmi = if p_xy < 1.0 { pmi / -p_xy.log2() } else { 1.0 };
+    let t_score = (xy - x * y / n) / xy.sqrt();
+    let log_likelihood = log_likelihood(xy, x - xy, y - xy, (n - x - y + xy).max(0.0));
+
+    Association {
+        pmi,
+        npmi,
+        t_score,
+        log_likelihood,
+    }
+}
+
+
+
+/// A scored bigram.
+#[derive(Debug, Clone)]
+pub struct Collocation<'a> {
+    pub bigram: &'a String,
+    pub count: u32,
+    pub association: Association,
+}
+
+
+
+/// Bigrams seen at least `min_count` times whose tokens are seen at least `min_unigram_count` times.
+/// `counts` must have orders 1 and 2.
+pub fn collocations(counts: &NgramCounts, min_count: u32, min_unigram_count: u32) -> Vec<Collocation<'_>> {
+    let n = total(&counts.orders[0]);
+
+    counts.orders[1]
+        .iter()
+        .filter(|&(_, &count)| count >= min_count)
+        .filter_map(|(bigram, &count)| {
+            let (x, y) = bigram.split_once(' ')?;
+            let x = counts.orders[0].get(x).copied().unwrap_or(0);
+            let y = counts.orders[0].get(y).copied().unwrap_or(0);
+            if x < min_unigram_count || y < min_unigram_count {
+                return None;
+            }
+            Some(Collocation {
+                bigram,
+                count,
+                association: association(count as u64, x as u64, y as u64, n),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,196 @@
+// vim:nowrap:
+
+// [A Simple Approach to Building Bilingual Lexicons](https://aclanthology.org/C96-1065.pdf) uses
+// sentence co-occurrence and the Dice coefficient.
+// Counts are sentence frequencies: a pair seen twice in the same sentence pair counts once.
+// Two passes, the first finds the sentence frequencies of each side so that rare words never get
+// into the pair table, the second counts the pairs.
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::io;
+
+use crate::parallel::for_each_pair;
+use crate::pipeline::{
+    Pipeline,
+    PipelineStats,
+};
+use crate::Counts;
+
+
+
+/// Words of one side kept for the second pass, numbered to keep the pair table small.
+#[derive(Debug, Clone, Default)]
+struct Side {
+    ids: HashMap<String, u32>,
+    words: Vec<String>,
+    df: Vec<u32>,
+}
+
+
+
+impl Side {
+    fn new(df: Counts, min_count: u32) -> Self {
+        let mut side = Side::default();
+        for (word, count) in df.into_iter().filter(|&(_, count)| count >= min_count) {
+            side.ids.insert(word.clone(), side.words.len() as u32);
+            side.words.push(word);
+            side.df.push(count);
+        }
+        side
+    }
+
+    /// Distinct ids of the kept tokens of a line.
+    fn ids(&self, line: &str, pipeline: &Pipeline, stats: &mut PipelineStats) -> Vec<u32> {
+        let ids: HashSet<u32> = pipeline
+            .tokens(line, stats)
+            .iter()
+            .filter_map(|token| self.ids.get(token.as_ref()).copied())
+            .collect();
+        ids.into_iter().collect()
+    }
+}
+
+
+
+/// Distinct tokens of a line.
+fn distinct(line: &str, pipeline: &Pipeline, stats: &mut PipelineStats) -> HashSet<String> {
+    pipeline
+        .tokens(line, stats)
+        .into_iter()
+        .map(|token| token.into_owned())
+        .collect()
+}
+
+
+
+/// How many sentence pairs each source and target word pair co-occurs in.
+#[derive(Debug, Clone, Default)]
+pub struct CoocCounts {
+    source: Side,
+    target: Side,
+    pairs: HashMap<(u32, u32), u32>,
+    /// Number of sentence pairs.
+    pub lines: u64,
+    /// Number of times the pair table outgrew its budget.
+    pub prunings: u64,
+    /// Pairs seen at most this many times were dropped by pruning and the counts of the others may be
+    /// underestimated by as much.
+    pub prune_floor: u32,
+}
+
+
+
+/// A scored source and target word pair.
+#[derive(Debug, Clone)]
+pub struct CoocPair<'a> {
+    pub source: &'a String,
+    pub target: &'a String,
+    pub count: u32,
+    pub source_df: u32,
+    pub target_df: u32,
+    /// 2 c(s, t) / (df(s) + df(t))
+    pub dice: f64,
+    /// Pointwise mutual information in bits.
+    pub pmi: f64,
+}
+
+
+
+impl CoocCounts {
+    pub fn pairs(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Pairs seen at least `min_count` times, scored and grouped by source word with the best
+    /// `per_source` pairs of each source word according to `score`.
+    pub fn scored<F>(&self, min_count: u32, per_source: Option<usize>, score: F) -> Vec<CoocPair<'_>>
+        where F: Fn(&CoocPair) -> f64
+    {
+        let n = self.lines as f64;
+        let mut by_source: HashMap<u32, Vec<CoocPair>> = HashMap::new();
+        for (&(s, t), &count) in self.pairs.iter().filter(|&(_, &count)| count >= min_count) {
+            let source_df = self.source.df[s as usize];
+            let target_df = self.target.df[t as usize];
+            let c = count as f64;
+            by_source
+                .entry(s)
+                .or_default()
+                .push(CoocPair {
+                    source: &self.source.words[s as usize],
+                    target: &self.target.words[t as usize],
+                    count,
+                    source_df,
+                    target_df,
+                    dice: 2.0 * c / (source_df as f64 + target_df as f64),
+                    pmi: (c * n / (source_df as f64 * target_df as f64)).log2(),
+                });
+        }
+
+        let mut scored: Vec<CoocPair> = by_source
+            .into_values()
+            .flat_map(|mut pairs| {
+                pairs.sort_unstable_by(|a, b| score(b).total_cmp(&score(a)).then(a.target.cmp(b.target)));
+                pairs.truncate(per_source.unwrap_or(usize::MAX));
+                pairs
+            })
+            .collect();
+        scored.sort_by(|a, b| b.source_df.cmp(&a.source_df).then(a.source.cmp(b.source)));
+
+        scored
+    }
+}
+
+
+
+/// Count the co-occurrences of source and target words seen in at least `min_word_count` sentence
+/// pairs.
+/// With `max_pairs`, the least frequent pairs are pruned whenever the table grows beyond it.
+/// The inputs are read twice so they can't be stdin.
+pub fn word_count_cooc(source: &str, target: &str, pipeline: &Pipeline, min_word_count: u32, max_pairs: Option<usize>) -> io::Result<(CoocCounts, PipelineStats)> {
+    if source == "-" || target == "-" {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "cooc reads its inputs twice, they can't be read from stdin"));
+    }
+
+    let mut stats = PipelineStats::default();
+    let mut source_df = Counts::new();
+    let mut target_df = Counts::new();
+    for_each_pair(source, target, |source_line, target_line| {
+        for (line, df) in [(source_line, &mut source_df), (target_line, &mut target_df)] {
+            for token in distinct(line, pipeline, &mut stats) {
+                *df.entry(token).or_insert(0u32) += 1u32;
+            }
+        }
+        Ok(())
+    })?;
+
+    let mut counts = CoocCounts {
+        source: Side::new(source_df, min_word_count),
+        target: Side::new(target_df, min_word_count),
+        ..CoocCounts::default()
+    };
+    // The first pass already collected the pipeline statistics.
+    let mut second_pass = PipelineStats::default();
+    counts.lines = for_each_pair(source, target, |source_line, target_line| {
+        let source_ids = counts.source.ids(source_line, pipeline, &mut second_pass);
+        let target_ids = counts.target.ids(target_line, pipeline, &mut second_pass);
+        for &s in &source_ids {
+            for &t in &target_ids {
+                *counts.pairs.entry((s, t)).or_insert(0u32) += 1u32;
+            }
+        }
+        if let Some(max_pairs) = max_pairs {
+            while counts.pairs.len() > max_pairs {
+                counts.prunings += 1;
+                counts.prune_floor += 1;
+                let floor = counts.prune_floor;
+                counts.pairs.retain(|_, &mut count| count > floor);
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok((counts, stats))
+}
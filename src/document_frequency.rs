@@ -0,0 +1,123 @@
+// vim:nowrap:
+
+// [tf–idf](https://en.wikipedia.org/wiki/Tf%E2%80%93idf)
+
+use std::collections::{
+    HashMap,
+    HashSet,
+};
+use std::io::{
+    BufRead,
+    self,
+};
+
+use crate::pipeline::{
+    Pipeline,
+    PipelineStats,
+};
+use crate::try_get_reader;
+
+
+
+/// What makes a document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Documents {
+    /// Each line, or JSONL record, is a document.
+    #[default]
+    Lines,
+    /// Documents are separated by blank lines.
+    Paragraphs,
+}
+
+
+
+/// Total count and document frequency of a token.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TermStats {
+    pub count: u32,
+    pub df: u32,
+}
+
+
+
+impl TermStats {
+    /// Inverse document frequency `ln(documents / df)`.
+    pub fn idf(&self, documents: u64) -> f64 {
+        (documents as f64 / self.df as f64).ln()
+    }
+
+    /// Corpus level tf-idf `count * idf`.
+    pub fn tf_idf(&self, documents: u64) -> f64 {
+        self.count as f64 * self.idf(documents)
+    }
+}
+
+
+
+/// Token statistics over a collection of documents.
+#[derive(Debug, Clone, Default)]
+pub struct DocumentCounts {
+    pub documents: u64,
+    pub terms: HashMap<String, TermStats>,
+}
+
+
+
+impl DocumentCounts {
+    fn end_document(&mut self, seen: &mut HashSet<String>) {
+        self.documents += 1;
+        for token in seen.drain() {
+            self.terms.entry(token).or_default().df += 1;
+        }
+    }
+}
+
+
+
+/// Buffer - While - for-loop through the shared tokenizer pipeline counting document frequencies.
+pub fn word_count_documents(filename: &Option<String>, pipeline: &Pipeline, documents: Documents) -> io::Result<(DocumentCounts, PipelineStats)> {
+    let mut counts = DocumentCounts::default();
+    let mut stats = PipelineStats::default();
+    // Tokens of the current document.
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut empty = true;
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        if documents == Documents::Paragraphs && line.trim().is_empty() {
+            if !empty {
+                counts.end_document(&mut seen);
+                empty = true;
+            }
+            line.clear();
+            continue;
+        }
+
+        // JSONL records without text are not documents.
+        let skipped = stats.invalid_json + stats.missing_fields;
+        let tokens = pipeline.tokens(&line, &mut stats);
+        if stats.invalid_json + stats.missing_fields > skipped {
+            line.clear();
+            continue;
+        }
+
+        for token in tokens {
+            counts.terms.entry(token.to_string()).or_default().count += 1;
+            if !seen.contains(token.as_ref()) {
+                seen.insert(token.into_owned());
+            }
+        }
+        empty = false;
+        if documents == Documents::Lines {
+            counts.end_document(&mut seen);
+        }
+        line.clear();
+    }
+
+    if documents == Documents::Paragraphs && !empty {
+        counts.end_document(&mut seen);
+    }
+
+    Ok((counts, stats))
+}
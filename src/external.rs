@@ -0,0 +1,385 @@
+// vim:nowrap:
+
+// [External sorting](https://en.wikipedia.org/wiki/External_sorting)
+// Partial counts are spilled to disk sorted by token as `token\tcount` lines and k-way merged.
+// At most `MAX_FAN_IN` spills are opened at once, more are first merged into intermediate spills.
+// Sorting the merged counts by count is a second external sort of the same spills whose keys are
+// the inverted count, zero padded, followed by the token.
+// Tokens never contain whitespace so the format is unambiguous.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{
+    self,
+    File,
+    OpenOptions,
+};
+use std::io::{
+    BufRead,
+    BufReader,
+    BufWriter,
+    Write,
+    self,
+};
+use std::mem::{
+    self,
+    size_of,
+};
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::process;
+use std::sync::atomic::{
+    AtomicUsize,
+    Ordering,
+};
+
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+/// Maximum number of spills merged at once.
+pub const MAX_FAN_IN: usize = 64;
+
+/// Number of the next temporary file of this process.
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+
+
+/// Rough heap footprint of one entry in `Counts`, including the hash table's bookkeeping.
+fn entry_size(word: &str) -> usize {
+    word.len() + size_of::<String>() + size_of::<u32>() + size_of::<u64>()
+}
+
+
+
+/// Parse a memory size such as `512M`, `2G` or `1048576`.
+pub fn parse_memory(size: &str) -> Result<usize, String> {
+    let size = size.trim();
+    let (number, multiplier) = match size.char_indices().last() {
+        Some((i, 'k')) | Some((i, 'K')) => (&size[..i], 1usize << 10),
+        Some((i, 'm')) | Some((i, 'M')) => (&size[..i], 1usize << 20),
+        Some((i, 'g')) | Some((i, 'G')) => (&size[..i], 1usize << 30),
+        _ => (size, 1usize),
+    };
+
+    number
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .filter(|&n| n > 0)
+        .ok_or_else(|| format!("invalid memory size: {}", size))
+}
+
+
+
+/// Sorted partial counts spilled to temporary files.
+/// The files are removed when this is dropped.
+pub struct Spills {
+    tmp_dir: PathBuf,
+    files: Vec<PathBuf>,
+    written: usize,
+}
+
+
+
+impl Drop for Spills {
+    fn drop(&mut self) {
+        for file in &self.files {
+            let _ = fs::remove_file(file);
+        }
+    }
+}
+
+
+
+impl Spills {
+    fn new(tmp_dir: &Path) -> Self {
+        Spills {
+            tmp_dir: tmp_dir.to_owned(),
+            files: Vec::new(),
+            written: 0,
+        }
+    }
+
+    /// Number of temporary files written, intermediate merges included.
+    pub fn len(&self) -> usize {
+        self.written
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.written == 0
+    }
+
+    /// Create a new temporary file, never reusing an existing one since the names are predictable.
+    fn create(&mut self) -> io::Result<BufWriter<File>> {
+        let path = self.tmp_dir.join(format!("get_voc.{}.{}.tmp", process::id(), NEXT_FILE.fetch_add(1, Ordering::Relaxed)));
+        let out = BufWriter::new(OpenOptions::new().write(true).create_new(true).open(&path)?);
+        self.files.push(path);
+        self.written += 1;
+
+        Ok(out)
+    }
+
+    fn spill(&mut self, counts: &mut Counts) -> io::Result<()> {
+        let mut out = self.create()?;
+
+        // Taking the map also releases its table.
+        let mut words: Vec<_> = mem::take(counts).into_iter().collect();
+        words.sort_unstable();
+        for (word, count) in words {
+            writeln!(out, "{}\t{}", word, count)?;
+        }
+
+        out.flush()
+    }
+
+    /// Merge `files` into a new spill.
+    fn merge_into_spill(&mut self, files: &[PathBuf]) -> io::Result<()> {
+        let mut out = self.create()?;
+        for entry in Merge::open(files)? {
+            let (word, count) = entry?;
+            writeln!(out, "{}\t{}", word, count)?;
+        }
+
+        out.flush()
+    }
+
+    /// K-way merge of all spills yielding `(token, count)` in token order.
+    /// The oldest spills are merged `MAX_FAN_IN` at a time until at most `MAX_FAN_IN` remain.
+    pub fn merge(&mut self) -> io::Result<Merge> {
+        while self.files.len() > MAX_FAN_IN {
+            let files: Vec<PathBuf> = self.files.drain(..MAX_FAN_IN).collect();
+            let merged = self.merge_into_spill(&files);
+            for file in &files {
+                let _ = fs::remove_file(file);
+            }
+            merged?;
+        }
+
+        Merge::open(&self.files)
+    }
+}
+
+
+
+/// Iterator over the merged spills.
+pub struct Merge {
+    readers: Vec<BufReader<File>>,
+    // Smallest token first, along with its count and the reader it came from.
+    heap: BinaryHeap<Reverse<(String, u32, usize)>>,
+}
+
+
+
+impl Merge {
+    fn open(files: &[PathBuf]) -> io::Result<Self> {
+        let mut merge = Merge {
+            readers: Vec::with_capacity(files.len()),
+            heap: BinaryHeap::with_capacity(files.len()),
+        };
+        for file in files {
+            merge.readers.push(BufReader::new(File::open(file)?));
+            merge.advance(merge.readers.len() - 1)?;
+        }
+
+        Ok(merge)
+    }
+
+    /// Push the next entry of reader `i` on the heap.
+    fn advance(&mut self, i: usize) -> io::Result<()> {
+        let mut line = String::new();
+        if self.readers[i].read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let (word, count) = line
+            .trim_end_matches('\n')
+            .rsplit_once('\t')
+            .and_then(|(word, count)| Some((word, count.parse::<u32>().ok()?)))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Corrupted spill file"))?;
+        self.heap.push(Reverse((word.to_owned(), count, i)));
+
+        Ok(())
+    }
+}
+
+
+
+impl Iterator for Merge {
+    type Item = io::Result<(String, u32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((word, mut count, i)) = self.heap.pop()?;
+        if let Err(e) = self.advance(i) {
+            return Some(Err(e));
+        }
+        while let Some(Reverse((next, _, _))) = self.heap.peek() {
+            if *next != word {
+                break;
+            }
+            let Reverse((_, more, j)) = self.heap.pop().expect("peeked");
+            count += more;
+            if let Err(e) = self.advance(j) {
+                return Some(Err(e));
+            }
+        }
+
+        Some(Ok((word, count)))
+    }
+}
+
+
+
+/// Buffer - While - for-loop that spills its counts to `tmp_dir` whenever their estimated size
+/// exceeds `max_memory` bytes.
+pub fn word_count_external(filename: &Option<String>, max_memory: usize, tmp_dir: &Path) -> io::Result<Spills> {
+    let mut spills = Spills::new(tmp_dir);
+    let mut counts = Counts::new();
+    let mut memory = 0usize;
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? != 0 {
+        for word in line.split(char::is_whitespace) {
+            if !word.is_empty() {
+                match counts.get_mut(word) {
+                    Some(count) => *count += 1u32,
+                    None => {
+                        memory += entry_size(word);
+                        counts.insert(word.to_owned(), 1u32);
+                    }
+                }
+            }
+        }
+        line.clear();
+
+        if memory > max_memory {
+            spills.spill(&mut counts)?;
+            memory = 0;
+        }
+    }
+
+    if !counts.is_empty() {
+        spills.spill(&mut counts)?;
+    }
+
+    Ok(spills)
+}
+
+
+
+/// Key of `word` sorting the highest counts first then the tokens alphabetically.
+fn by_count_key(word: &str, count: u32) -> String {
+    format!("{:010}\t{}", u32::MAX - count, word)
+}
+
+
+
+/// Merged spills sorted by count, yielding `(token, count)` like `sort_counts`.
+/// The spills are removed when this is dropped.
+pub struct ByCount {
+    merge: Merge,
+    spills: Spills,
+}
+
+
+
+impl ByCount {
+    /// Number of temporary files written.
+    pub fn spills(&self) -> usize {
+        self.spills.len()
+    }
+}
+
+
+
+impl Iterator for ByCount {
+    type Item = io::Result<(String, u32)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = self.merge.next()?;
+        Some(entry.map(|(key, count)| {
+            let word = key.split_once('\t').map_or(key.as_str(), |(_, word)| word);
+            (word.to_owned(), count)
+        }))
+    }
+}
+
+
+
+/// Sort `merged` by decreasing count then alphabetically, spilling to `tmp_dir` whenever the
+/// estimated size of the entries in memory exceeds `max_memory` bytes.
+pub fn sort_by_count<I>(merged: I, max_memory: usize, tmp_dir: &Path) -> io::Result<ByCount>
+    where I: Iterator<Item = io::Result<(String, u32)>>
+{
+    let mut spills = Spills::new(tmp_dir);
+    let mut keys = Counts::new();
+    let mut memory = 0usize;
+
+    for entry in merged {
+        let (word, count) = entry?;
+        let key = by_count_key(&word, count);
+        memory += entry_size(&key);
+        keys.insert(key, count);
+
+        if memory > max_memory {
+            spills.spill(&mut keys)?;
+            memory = 0;
+        }
+    }
+
+    if !keys.is_empty() {
+        spills.spill(&mut keys)?;
+    }
+
+    Ok(ByCount {
+        merge: spills.merge()?,
+        spills,
+    })
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_more_than_fan_in() {
+        let tmp_dir = std::env::temp_dir().join(format!("get_voc_test.{}", process::id()));
+        fs::create_dir_all(&tmp_dir).unwrap();
+        let input = tmp_dir.join("input.txt");
+        let mut expected = Counts::new();
+        {
+            let mut out = BufWriter::new(File::create(&input).unwrap());
+            for i in 0..1000u32 {
+                let line = format!("w{} w{} shared", i % 97, i % 13);
+                for word in line.split(' ') {
+                    *expected.entry(word.to_owned()).or_insert(0u32) += 1u32;
+                }
+                writeln!(out, "{}", line).unwrap();
+            }
+        }
+
+        // Spill after every line.
+        let mut spills = word_count_external(&Some(input.to_string_lossy().into_owned()), 1, &tmp_dir).unwrap();
+        assert!(spills.len() > 2 * MAX_FAN_IN);
+        let merged: Vec<(String, u32)> = spills.merge().unwrap().map(Result::unwrap).collect();
+        let mut sorted: Vec<(String, u32)> = expected.clone().into_iter().collect();
+        sorted.sort_unstable();
+        assert_eq!(merged, sorted);
+
+        let by_count: Vec<(String, u32)> = sort_by_count(merged.into_iter().map(Ok), 64, &tmp_dir).unwrap().map(Result::unwrap).collect();
+        sorted.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        assert_eq!(by_count, sorted);
+
+        drop(spills);
+        fs::remove_file(&input).unwrap();
+        assert_eq!(fs::read_dir(&tmp_dir).unwrap().count(), 0);
+        fs::remove_dir(&tmp_dir).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+// vim:nowrap:
+
+// [Intelligent Selection of Language Model Training Data](https://aclanthology.org/P10-2041.pdf) Moore and Lewis.
+// A line looks in-domain when its cross-entropy under an in-domain model is low compared to its
+// cross-entropy under a general model: H_in(s) - H_general(s) < threshold.
+// Lines dominated by rare or unknown tokens have a high OOV ratio and a low mean log-frequency.
+
+use std::io::{
+    BufRead,
+    Write,
+    self,
+};
+
+use crate::arpa::BackoffModel;
+use crate::parallel::for_each_pair;
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+/// Per word cross-entropy in log10 of a sentence, `</s>` included if `eos`.
+/// Without `</s>`, an empty sentence has no cross-entropy and gets 0.
+pub fn cross_entropy<S: AsRef<str>>(model: &BackoffModel, tokens: &[S], eos: bool) -> f64 {
+    let (log_prob, words) = if eos { model.sentence_log_prob(tokens) } else { model.words_log_prob(tokens) };
+    if words == 0 { 0.0 } else { -log_prob / words as f64 }
+}
+
+
+
+/// Cross-entropies of a line under both models.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CrossEntropies {
+    pub in_domain: f64,
+    pub general: f64,
+}
+
+
+
+impl CrossEntropies {
+    /// Moore-Lewis score, the lower the more in-domain.
+    pub fn difference(&self) -> f64 {
+        self.in_domain - self.general
+    }
+}
+
+
+
+/// In-domain and general models.
+#[derive(Debug, Clone)]
+pub struct MooreLewis {
+    pub in_domain: BackoffModel,
+    pub general: BackoffModel,
+    /// Score `</s>`, unigram models of vocabularies never saw it and give it the probability of
+    /// `<unk>`, which differs between the models.
+    pub eos: bool,
+}
+
+
+
+impl MooreLewis {
+    pub fn score<S: AsRef<str>>(&self, tokens: &[S]) -> CrossEntropies {
+        CrossEntropies {
+            in_domain: cross_entropy(&self.in_domain, tokens, self.eos),
+            general: cross_entropy(&self.general, tokens, self.eos),
+        }
+    }
+}
+
+
+
+/// How well a vocabulary covers a line.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OovScore {
+    /// Fraction of the tokens missing from the vocabulary, 0 for an empty line.
+    pub oov_ratio: f64,
+    /// Mean of `log10(1 + count)`, 0 for an empty line.
+    pub mean_log_frequency: f64,
+}
+
+
+
+/// Coverage of `tokens` by the tokens of `vocabulary` seen at least `min_count` times.
+pub fn oov_score<S: AsRef<str>>(tokens: &[S], vocabulary: &Counts, min_count: u32) -> OovScore {
+    if tokens.is_empty() {
+        return OovScore::default();
+    }
+
+    let (oov, log_frequency) = tokens
+        .iter()
+        .map(|token| vocabulary.get(token.as_ref()).copied().filter(|&count| count >= min_count).unwrap_or(0))
+        .fold((0usize, 0f64), |(oov, log_frequency), count| {
+            (oov + usize::from(count == 0), log_frequency + (1.0 + count as f64).log10())
+        });
+
+    OovScore {
+        oov_ratio: oov as f64 / tokens.len() as f64,
+        mean_log_frequency: log_frequency / tokens.len() as f64,
+    }
+}
+
+
+
+/// Where the kept lines, the rejected lines and the per line scores go.
+pub struct FilterOutputs {
+    pub kept: Box<dyn Write>,
+    pub rejected: Option<Box<dyn Write>>,
+    pub scores: Option<Box<dyn Write>>,
+}
+
+
+
+/// Number of lines read and kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FilterStats {
+    pub lines: u64,
+    pub kept: u64,
+}
+
+
+
+impl FilterStats {
+    pub fn rejected(&self) -> u64 {
+        self.lines - self.kept
+    }
+}
+
+
+
+/// Write `line` to the kept or rejected lines, adding the newline a last line may be missing.
+fn write_line(out: &mut dyn Write, line: &str) -> io::Result<()> {
+    out.write_all(line.as_bytes())?;
+    if !line.ends_with('\n') {
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+
+
+impl FilterOutputs {
+    /// Route a line and write its `scores` columns.
+    pub fn write(&mut self, line: &str, keep: bool, scores: &str) -> io::Result<()> {
+        if keep {
+            write_line(&mut self.kept, line)?;
+        }
+        else if let Some(rejected) = &mut self.rejected {
+            write_line(rejected, line)?;
+        }
+        if let Some(out) = &mut self.scores {
+            writeln!(out, "{}\t{}", if keep { "kept" } else { "rejected" }, scores)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.kept.flush()?;
+        for out in [&mut self.rejected, &mut self.scores].into_iter().flatten() {
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+
+
+/// Buffer - While - loop through the lines of `filename` routing them according to `decide`, which
+/// returns whether to keep a line and its scores columns.
+pub fn filter_lines<F>(filename: &Option<String>, outputs: &mut FilterOutputs, mut decide: F) -> io::Result<FilterStats>
+    where F: FnMut(&str) -> (bool, String)
+{
+    let mut stats = FilterStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        stats.lines += 1;
+        let (keep, scores) = decide(&line);
+        if keep {
+            stats.kept += 1;
+        }
+        outputs.write(&line, keep, &scores)?;
+        line.clear();
+    }
+    outputs.flush()?;
+
+    Ok(stats)
+}
+
+
+
+/// Like `filter_lines` for sentence aligned files whose pairs are kept or rejected together.
+/// The scores go to the scores of `source_outputs`.
+pub fn filter_pairs<F>(source: &str, target: &str, source_outputs: &mut FilterOutputs, target_outputs: &mut FilterOutputs, mut decide: F) -> io::Result<FilterStats>
+    where F: FnMut(&str, &str) -> (bool, String)
+{
+    let mut stats = FilterStats::default();
+
+    stats.lines = for_each_pair(source, target, |source_line, target_line| {
+        let (keep, scores) = decide(source_line, target_line);
+        if keep {
+            stats.kept += 1;
+        }
+        source_outputs.write(source_line, keep, &scores)?;
+        target_outputs.write(target_line, keep, &scores)
+    })?;
+    source_outputs.flush()?;
+    target_outputs.flush()?;
+
+    Ok(stats)
+}
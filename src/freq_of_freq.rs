@@ -0,0 +1,151 @@
+// vim:nowrap:
+
+// [Good–Turing frequency estimation](https://en.wikipedia.org/wiki/Good%E2%80%93Turing_frequency_estimation)
+
+use std::collections::BTreeMap;
+
+use crate::Counts;
+
+
+
+/// Number of types seen exactly `count` times, indexed by `count`.
+pub type FreqOfFreq = BTreeMap<u32, usize>;
+
+
+
+/// Count-of-counts table of a vocabulary.
+pub fn freq_of_freq(counts: &Counts) -> FreqOfFreq {
+    counts
+        .values()
+        .fold(FreqOfFreq::new(), |mut fof: FreqOfFreq, &count: &u32| {
+            *fof.entry(count).or_insert(0usize) += 1usize;
+            fof
+        })
+}
+
+
+
+/// `k` such that `base^k <= count < base^(k+1)`.
+/// The floating point logarithm can land just below an integer for exact powers, e.g.
+/// `log10(1000) = 2.9999999999999996`, so it is only a first guess corrected with the same
+/// powers that bound the bins.
+fn log_floor(count: u32, base: f64) -> u32 {
+    let count = count as f64;
+    let mut bin = count.log(base).floor().max(0.0) as i32;
+    while bin > 0 && base.powi(bin) > count {
+        bin -= 1;
+    }
+    while base.powi(bin + 1) <= count {
+        bin += 1;
+    }
+    bin as u32
+}
+
+
+
+/// Group counts into logarithmic bins `[base^k, base^(k+1))`, `base` must be greater than 1.
+/// Returns `(low, high, number_of_types)` with inclusive bounds, skipping empty bins.
+pub fn log_bin(fof: &FreqOfFreq, base: f64) -> Vec<(u32, u32, usize)> {
+    let mut bins: BTreeMap<u32, usize> = BTreeMap::new();
+    for (&count, &types) in fof {
+        let bin = log_floor(count, base);
+        *bins.entry(bin).or_insert(0usize) += types;
+    }
+
+    bins.into_iter()
+        .map(|(bin, types)| {
+            let low = base.powi(bin as i32).ceil() as u32;
+            let high = base.powi(bin as i32 + 1).ceil() as u32 - 1;
+            (low, high.max(low), types)
+        })
+        .collect()
+}
+
+
+
+/// Counts above this are reliable and left undiscounted, see Katz (1987).
+pub const KATZ_CUTOFF: u32 = 5;
+
+
+
+/// Good-Turing estimates with Katz's cutoff.
+#[derive(Debug, Clone)]
+pub struct GoodTuring {
+    /// Total number of tokens.
+    pub tokens: u64,
+    /// Probability mass reserved for unseen types, `N1 / N`.
+    pub unseen_mass: f64,
+    /// Adjusted counts, for `r <= KATZ_CUTOFF` the Turing estimate `(r + 1) * N(r+1) / N(r)`
+    /// renormalized so that only the singleton mass is taken from the low counts, `r` otherwise.
+    /// Falls back to `r` when the estimate isn't positive, e.g. when `N(r+1)` is zero, and never
+    /// exceeds `r` nor the adjusted count of a higher count.
+    pub adjusted: BTreeMap<u32, f64>,
+}
+
+
+
+/// Good-Turing estimates from a count-of-counts table.
+/// [Estimation of probabilities from sparse data](https://doi.org/10.1109/TASSP.1987.1165125) Katz.
+pub fn good_turing(fof: &FreqOfFreq) -> GoodTuring {
+    let tokens: u64 = fof.iter().map(|(&count, &types)| count as u64 * types as u64).sum();
+    let singletons = fof.get(&1).copied().unwrap_or(0);
+    let unseen_mass = if tokens == 0 { 0.0 } else { singletons as f64 / tokens as f64 };
+
+    let n = |count: u32| fof.get(&count).copied().unwrap_or(0) as f64;
+    // Share of the singleton mass the counts above the cutoff would have given away.
+    let above = (KATZ_CUTOFF + 1) as f64 * n(KATZ_CUTOFF + 1) / n(1);
+    let discount = |count: u32| -> f64 {
+        let r = count as f64;
+        if count > KATZ_CUTOFF || above.is_nan() || above >= 1.0 {
+            return r;
+        }
+        let turing = (r + 1.0) * n(count + 1) / n(count);
+        let adjusted = (turing - r * above) / (1.0 - above);
+        if adjusted > 0.0 { adjusted.min(r) } else { r }
+    };
+
+    let mut adjusted: BTreeMap<u32, f64> = BTreeMap::new();
+    let mut ceiling = f64::INFINITY;
+    for &count in fof.keys().rev() {
+        ceiling = discount(count).min(ceiling);
+        adjusted.insert(count, ceiling);
+    }
+
+    GoodTuring {
+        tokens,
+        unseen_mass,
+        adjusted,
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn log_bin_exact_powers() {
+        let fof: FreqOfFreq = [(1, 1), (999, 2), (1000, 3)].into_iter().collect();
+        assert_eq!(log_bin(&fof, 10.0), vec![(1, 9, 1), (100, 999, 2), (1000, 9999, 3)]);
+        let fof: FreqOfFreq = [(242, 4), (243, 5)].into_iter().collect();
+        assert_eq!(log_bin(&fof, 3.0), vec![(81, 242, 4), (243, 728, 5)]);
+    }
+
+    #[test]
+    fn good_turing_is_monotone() {
+        let fof: FreqOfFreq = [(1, 2), (2, 1), (99, 1), (100, 2)].into_iter().collect();
+        let gt = good_turing(&fof);
+        assert_eq!(gt.adjusted[&100], 100.0);
+        assert_eq!(gt.adjusted[&99], 99.0);
+        assert!(gt.adjusted[&1] <= gt.adjusted[&2]);
+        assert!((gt.unseen_mass - 2.0 / 303.0).abs() < 1e-12);
+
+        let fof: FreqOfFreq = [(1, 120), (2, 40), (3, 24), (4, 13), (5, 15), (6, 5), (7, 11)].into_iter().collect();
+        let gt = good_turing(&fof);
+        let adjusted: Vec<f64> = gt.adjusted.values().copied().collect();
+        assert!(adjusted.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!((adjusted[0] - 5.0 / 9.0).abs() < 1e-12);
+        assert_eq!(adjusted[6], 7.0);
+    }
+}
@@ -0,0 +1,143 @@
+// vim:nowrap:
+
+// [HyperLogLog: the analysis of a near-optimal cardinality estimation algorithm](http://algo.inria.fr/flajolet/Publications/FlFuGaMe07.pdf)
+// Uses a 64 bit hash so the large range correction of the original paper is not needed.
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{
+    Hash,
+    Hasher,
+};
+use std::io::{
+    BufRead,
+    self,
+};
+
+use crate::pipeline::{
+    Pipeline,
+    PipelineStats,
+};
+use crate::try_get_reader;
+
+
+
+pub const MIN_PRECISION: u8 = 4;
+pub const MAX_PRECISION: u8 = 18;
+
+
+
+/// Distinct count estimator using `2^precision` one byte registers.
+pub struct HyperLogLog {
+    precision: u8,
+    registers: Vec<u8>,
+}
+
+
+
+impl HyperLogLog {
+    /// `precision` is clamped to `[MIN_PRECISION, MAX_PRECISION]`.
+    pub fn new(precision: u8) -> Self {
+        let precision = precision.clamp(MIN_PRECISION, MAX_PRECISION);
+        HyperLogLog {
+            precision,
+            registers: vec![0u8; 1 << precision],
+        }
+    }
+
+    pub fn precision(&self) -> u8 {
+        self.precision
+    }
+
+    pub fn insert<T: Hash + ?Sized>(&mut self, item: &T) {
+        let mut hasher = DefaultHasher::new();
+        item.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let index = (hash >> (64 - self.precision)) as usize;
+        // Position of the leftmost 1 in the remaining bits, the sentinel bit bounds it.
+        let rest = (hash << self.precision) | (1u64 << (self.precision - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        if rank > self.registers[index] {
+            self.registers[index] = rank;
+        }
+    }
+
+    /// Estimated number of distinct items inserted.
+    pub fn estimate(&self) -> f64 {
+        let m = self.registers.len() as f64;
+        let alpha = match self.registers.len() {
+            16 => 0.673,
+            32 => 0.697,
+            64 => 0.709,
+            _ => 0.7213 / (1.0 + 1.079 / m),
+        };
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let raw = alpha * m * m / sum;
+
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        if raw <= 2.5 * m && zeros > 0 {
+            // Linear counting for small cardinalities.
+            m * (m / zeros as f64).ln()
+        }
+        else {
+            raw
+        }
+    }
+
+    /// Relative standard error `1.04 / sqrt(m)`.
+    pub fn standard_error(&self) -> f64 {
+        1.04 / (self.registers.len() as f64).sqrt()
+    }
+}
+
+
+
+/// Buffer - While - for-loop estimating the number of types of the shared tokenizer pipeline.
+pub fn estimate_types(filename: &Option<String>, pipeline: &Pipeline, precision: u8) -> io::Result<(HyperLogLog, PipelineStats)> {
+    let mut hll = HyperLogLog::new(precision);
+    let mut stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        for token in pipeline.tokens(&line, &mut stats) {
+            hll.insert(&token);
+        }
+        line.clear();
+    }
+
+    Ok((hll, stats))
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(types: usize, precision: u8) -> HyperLogLog {
+        let mut hll = HyperLogLog::new(precision);
+        for _ in 0..2 {
+            for i in 0..types {
+                hll.insert(&format!("token{}", i));
+            }
+        }
+        hll
+    }
+
+    #[test]
+    fn estimate_within_error() {
+        assert_eq!(estimate(0, 14).estimate(), 0.0);
+        for (types, precision) in [(100, 14), (5_000, 10), (200_000, 14)] {
+            let hll = estimate(types, precision);
+            let error = (hll.estimate() - types as f64).abs() / types as f64;
+            assert!(error < 3.0 * hll.standard_error(), "{} types estimated as {}", types, hll.estimate());
+        }
+    }
+
+    #[test]
+    fn precision_is_clamped() {
+        assert_eq!(HyperLogLog::new(0).precision(), MIN_PRECISION);
+        assert_eq!(HyperLogLog::new(u8::MAX).precision(), MAX_PRECISION);
+    }
+}
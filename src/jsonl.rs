@@ -0,0 +1,66 @@
+// vim:nowrap:
+
+// [JSON Lines](https://jsonlines.org/)
+
+use std::str::FromStr;
+
+use serde_json::Value;
+
+
+
+/// Dotted path to a field of a json document, e.g. `text` or `meta.langs.0`.
+/// Numeric components index arrays.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonPath(Vec<String>);
+
+
+
+impl FromStr for JsonPath {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        if path.split('.').any(str::is_empty) {
+            return Err(format!("invalid json path: {}", path));
+        }
+
+        Ok(JsonPath(path.split('.').map(str::to_owned).collect()))
+    }
+}
+
+
+
+impl JsonPath {
+    pub fn get<'v>(&self, document: &'v Value) -> Option<&'v Value> {
+        self.0
+            .iter()
+            .try_fold(document, |value, key| match value {
+                Value::Object(object) => object.get(key),
+                Value::Array(array) => key.parse::<usize>().ok().and_then(|i| array.get(i)),
+                _ => None,
+            })
+    }
+
+    /// Textual value at this path.
+    /// Strings are returned as is, numbers and booleans are formatted and arrays of strings are
+    /// joined with newlines.
+    pub fn text(&self, document: &Value) -> Option<String> {
+        match self.get(document)? {
+            Value::String(text) => Some(text.to_owned()),
+            Value::Number(number) => Some(number.to_string()),
+            Value::Bool(boolean) => Some(boolean.to_string()),
+            Value::Array(array) => array
+                .iter()
+                .map(|value| value.as_str())
+                .collect::<Option<Vec<&str>>>()
+                .map(|texts| texts.join("\n")),
+            _ => None,
+        }
+    }
+}
+
+
+
+/// Parse one line of a JSONL file.
+pub fn parse_line(line: &str) -> Option<Value> {
+    serde_json::from_str(line).ok()
+}
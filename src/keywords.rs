@@ -0,0 +1,135 @@
+// vim:nowrap:
+
+// [Accurate methods for the statistics of surprise and coincidence](https://aclanthology.org/J93-1003.pdf)
+// [Log Ratio](http://cass.lancs.ac.uk/log-ratio-an-informal-introduction/)
+// Both corpora form a 2x2 contingency table per token:
+//               target   reference
+//   token       a        b
+//   other       c - a    d - b
+
+use crate::vocabulary::total;
+use crate::Counts;
+
+
+
+/// Log-likelihood critical values with one degree of freedom, as `(p, value)`.
+pub const CRITICAL_VALUES: [(f64, f64); 4] = [
+    (0.05, 3.84),
+    (0.01, 6.63),
+    (0.001, 10.83),
+    (0.0001, 15.13),
+];
+
+
+
+/// Log-likelihood critical value for the significance level `p`.
+pub fn critical_value(p: f64) -> Option<f64> {
+    CRITICAL_VALUES
+        .iter()
+        .find(|&&(level, _)| level == p)
+        .map(|&(_, value)| value)
+}
+
+
+
+/// `*` for each critical value exceeded by `ll`.
+pub fn significance(ll: f64) -> &'static str {
+    match CRITICAL_VALUES.iter().filter(|&&(_, value)| ll >= value).count() {
+        0 => "",
+        1 => "*",
+        2 => "**",
+        3 => "***",
+        _ => "****",
+    }
+}
+
+
+
+/// Keyness of a token in the target corpus compared to the reference corpus.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keyness {
+    /// Dunning's log-likelihood G2.
+    pub log_likelihood: f64,
+    /// Pearson's chi-square without continuity correction.
+    pub chi_square: f64,
+    /// Binary log of the ratio of relative frequencies, positive when overused in the target.
+    /// A zero count is replaced by 0.5.
+    pub log_ratio: f64,
+}
+
+
+
+/// Keyness of a token seen `a` times in `c` target tokens and `b` times in `d` reference tokens.
+pub fn keyness(a: u64, b: u64, c: u64, d: u64) -> Keyness {
+    let (a, b, c, d) = (a as f64, b as f64, c as f64, d as f64);
+    let n = c + d;
+
+    let e1 = c * (a + b) / n;
+    let e2 = d * (a + b) / n;
+    let term = |observed: f64, expected: f64| {
+        if observed > 0.0 { observed * (observed / expected).ln() } else { 0.0 }
+    };
+    let log_likelihood = 2.0 * (term(a, e1) + term(b, e2));
+
+    let (a_, b_) = (c - a, d - b);
+    let denominator = (a + b) * (a_ + b_) * c * d;
+    let chi_square = if denominator > 0.0 { n * (a * b_ - b * a_).powi(2) / denominator } else { 0.0 };
+
+    let smooth = |count: f64| if count > 0.0 { count } else { 0.5 };
+    let log_ratio = ((smooth(a) / c) / (smooth(b) / d)).log2();
+
+    Keyness {
+        log_likelihood,
+        chi_square,
+        log_ratio,
+    }
+}
+
+
+
+/// A token with its counts and keyness.
+#[derive(Debug, Clone)]
+pub struct Keyword<'a> {
+    pub token: &'a String,
+    pub target: u32,
+    pub reference: u32,
+    pub keyness: Keyness,
+}
+
+
+
+/// Tokens seen at least `min_count` times ranked by decreasing log-likelihood.
+/// Tokens underused in the target, including those only in the reference, are only kept if
+/// `underused`.
+pub fn keywords<'a>(target: &'a Counts, reference: &'a Counts, min_count: u32, underused: bool) -> Vec<Keyword<'a>> {
+    let c = total(target);
+    let d = total(reference);
+
+    let reference_only = reference
+        .iter()
+        .filter(|_| underused)
+        .filter(|&(token, _)| !target.contains_key(token))
+        .map(|(token, _)| (token, &0u32));
+    let mut keywords: Vec<Keyword> = target
+        .iter()
+        .chain(reference_only)
+        .map(|(token, &count)| {
+            let other = reference.get(token).copied().unwrap_or(0);
+            Keyword {
+                token,
+                target: count,
+                reference: other,
+                keyness: keyness(count as u64, other as u64, c, d),
+            }
+        })
+        .filter(|keyword| keyword.target.max(keyword.reference) >= min_count)
+        .filter(|keyword| underused || keyword.keyness.log_ratio > 0.0)
+        .collect();
+    keywords.sort_unstable_by(|a, b| {
+        b.keyness.log_likelihood
+            .total_cmp(&a.keyness.log_likelihood)
+            .then(a.token.cmp(b.token))
+    });
+
+    keywords
+}
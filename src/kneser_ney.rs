@@ -0,0 +1,280 @@
+// vim:nowrap:
+
+// [An Empirical Study of Smoothing Techniques for Language Modeling](https://dash.harvard.edu/bitstream/handle/1/25104739/tr-10-98.pdf) Chen and Goodman.
+// [Scalable Modified Kneser-Ney Language Model Estimation](https://aclanthology.org/P13-2121.pdf) Heafield et al.
+// Interpolated modified Kneser-Ney:
+//   p(w | h) = (a(h w) - D(a(h w))) / sum_x a(h x) + gamma(h) p(w | h')
+//   gamma(h) = (D1 N1(h .) + D2 N2(h .) + D3+ N3+(h .)) / sum_x a(h x)
+// where h' drops the first word of h and the adjusted counts `a` are the raw counts for the highest
+// order and n-grams starting with `<s>`, and the number of distinct left extensions otherwise.
+// Unigrams are interpolated with the uniform distribution over the vocabulary, `<unk>` included.
+// Since interpolation already happened, the ARPA backoff of a context is gamma.
+
+use std::collections::HashMap;
+
+use crate::arpa::{
+    ArpaEntry,
+    BOS_LOG_PROB,
+    UNK,
+};
+use crate::ngrams::{
+    NgramCounts,
+    BOS,
+};
+use crate::Counts;
+
+
+
+/// Discounts used when the count-of-counts can't estimate them, as KenLM's --discount_fallback.
+pub const FALLBACK_DISCOUNTS: [f64; 3] = [0.5, 1.0, 1.5];
+
+
+
+/// Discounts of the adjusted counts 1, 2 and 3+ of one order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Discounts(pub [f64; 3]);
+
+
+
+impl Discounts {
+    /// Estimates from the number of n-grams with adjusted counts 1 to 4.
+    /// None when they are out of range, typically on small corpora.
+    pub fn estimate(counts: &Counts) -> Option<Self> {
+        let mut t = [0f64; 5];
+        for &count in counts.values() {
+            if (1..=4).contains(&count) {
+                t[count as usize] += 1.0;
+            }
+        }
+        let y = t[1] / (t[1] + 2.0 * t[2]);
+        let discounts = [
+            1.0 - 2.0 * y * t[2] / t[1],
+            2.0 - 3.0 * y * t[3] / t[2],
+            3.0 - 4.0 * y * t[4] / t[3],
+        ];
+        let valid = discounts
+            .iter()
+            .enumerate()
+            .all(|(i, &d)| d.is_finite() && d > 0.0 && d <= (i + 1) as f64);
+
+        if valid { Some(Discounts(discounts)) } else { None }
+    }
+
+    pub fn discount(&self, count: u32) -> f64 {
+        match count {
+            0 => 0.0,
+            1 => self.0[0],
+            2 => self.0[1],
+            _ => self.0[2],
+        }
+    }
+}
+
+
+
+/// All but the first word of an n-gram.
+fn suffix(ngram: &str) -> &str {
+    ngram.split_once(' ').map_or("", |(_, suffix)| suffix)
+}
+
+
+
+/// All but the last word of an n-gram.
+fn context(ngram: &str) -> &str {
+    ngram.rsplit_once(' ').map_or("", |(context, _)| context)
+}
+
+
+
+/// Kneser-Ney adjusted counts of n-grams counted with sentence boundaries.
+pub fn adjusted_counts(counts: &NgramCounts) -> NgramCounts {
+    let order = counts.order();
+    let mut adjusted = NgramCounts::new(order);
+    adjusted.orders[order - 1] = counts.orders[order - 1].clone();
+    for n in (1..order).rev() {
+        let lower = &mut adjusted.orders[n - 1];
+        for ngram in counts.orders[n].keys() {
+            *lower.entry(suffix(ngram).to_owned()).or_insert(0u32) += 1u32;
+        }
+        for (ngram, &count) in &counts.orders[n - 1] {
+            if ngram.starts_with(BOS) {
+                lower.insert(ngram.clone(), count);
+            }
+        }
+    }
+
+    adjusted
+}
+
+
+
+/// Sum and number of extensions with adjusted counts 1, 2 and 3+ of a context.
+#[derive(Debug, Clone, Copy, Default)]
+struct Extensions {
+    total: u64,
+    counts: [u64; 3],
+}
+
+
+
+impl Extensions {
+    /// Interpolation weight of the lower order.
+    fn gamma(&self, discounts: &Discounts) -> f64 {
+        discounts.0
+            .iter()
+            .zip(self.counts)
+            .map(|(d, n)| d * n as f64)
+            .sum::<f64>()
+            / self.total as f64
+    }
+}
+
+
+
+/// An interpolated modified Kneser-Ney model.
+#[derive(Debug, Clone)]
+pub struct KneserNey {
+    /// ARPA entries, `orders[i]` has the n-grams of order `i + 1` sorted by n-gram.
+    pub orders: Vec<Vec<ArpaEntry>>,
+    pub discounts: Vec<Discounts>,
+    /// Whether the discounts of each order are the fallback ones.
+    pub fallback: Vec<bool>,
+}
+
+
+
+/// Estimate a model from counts including `<s>` and `</s>`.
+pub fn kneser_ney(counts: &NgramCounts) -> KneserNey {
+    let order = counts.order();
+    let mut adjusted = adjusted_counts(counts);
+    // `<s>` is only ever a context.
+    adjusted.orders[0].remove(BOS);
+
+    let mut model = KneserNey {
+        orders: Vec::with_capacity(order),
+        discounts: Vec::with_capacity(order),
+        fallback: Vec::with_capacity(order),
+    };
+    for counts in &adjusted.orders {
+        let discounts = Discounts::estimate(counts);
+        model.fallback.push(discounts.is_none());
+        model.discounts.push(discounts.unwrap_or(Discounts(FALLBACK_DISCOUNTS)));
+    }
+
+    let mut extensions: Vec<HashMap<&str, Extensions>> = vec![HashMap::new(); order];
+    for (n, counts) in adjusted.orders.iter().enumerate() {
+        for (ngram, &count) in counts {
+            let extension = extensions[n].entry(context(ngram)).or_default();
+            extension.total += count as u64;
+            extension.counts[(count.min(3) - 1) as usize] += 1;
+        }
+    }
+
+    // Unigrams interpolated with the uniform distribution.
+    let vocabulary = (adjusted.orders[0].len() + usize::from(!adjusted.orders[0].contains_key(UNK))) as f64;
+    let unigram_gamma = extensions[0]
+        .get("")
+        .map_or(1.0, |extension| extension.gamma(&model.discounts[0]));
+    let mut probs: Vec<HashMap<&str, f64>> = vec![HashMap::new(); order];
+    probs[0].insert(UNK, unigram_gamma / vocabulary);
+
+    for (n, counts) in adjusted.orders.iter().enumerate() {
+        let discounts = model.discounts[n];
+        for (ngram, &count) in counts {
+            let extension = &extensions[n][context(ngram)];
+            let lower = if n == 0 {
+                unigram_gamma / vocabulary
+            }
+            else {
+                extension.gamma(&discounts) * probs[n - 1][suffix(ngram)]
+            };
+            let prob = (count as f64 - discounts.discount(count)) / extension.total as f64 + lower;
+            probs[n].insert(ngram, prob);
+        }
+    }
+
+    for n in 0..order {
+        let mut entries: Vec<ArpaEntry> = probs[n]
+            .iter()
+            .map(|(&ngram, &prob)| ArpaEntry {
+                ngram: ngram.to_owned(),
+                log_prob: prob.log10(),
+                backoff: None,
+            })
+            .collect();
+        if n == 0 {
+            entries.push(ArpaEntry {
+                ngram: BOS.to_owned(),
+                log_prob: BOS_LOG_PROB,
+                backoff: None,
+            });
+        }
+        if n + 1 < order {
+            for entry in entries.iter_mut() {
+                let gamma = extensions[n + 1]
+                    .get(entry.ngram.as_str())
+                    .map_or(1.0, |extension| extension.gamma(&model.discounts[n + 1]));
+                entry.backoff = Some(gamma.log10());
+            }
+        }
+        entries.sort_unstable_by(|a, b| a.ngram.cmp(&b.ngram));
+        model.orders.push(entries);
+    }
+
+    model
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arpa::BackoffModel;
+    use crate::ngrams::EOS;
+
+    fn model(corpus: &[&str], order: usize) -> KneserNey {
+        let mut counts = NgramCounts::new(order);
+        for line in corpus {
+            let mut tokens = vec![BOS];
+            tokens.extend(line.split(' '));
+            tokens.push(EOS);
+            counts.add(&tokens, 1).unwrap();
+        }
+        kneser_ney(&counts)
+    }
+
+    #[test]
+    fn discounts_estimate() {
+        let counts: Counts = [(1, 10), (2, 5), (3, 3), (4, 2), (7, 4)]
+            .into_iter()
+            .flat_map(|(count, ngrams)| (0..ngrams).map(move |i| (format!("{}-{}", count, i), count)))
+            .collect();
+        let Discounts(discounts) = Discounts::estimate(&counts).unwrap();
+        for (discount, expected) in discounts.iter().zip([0.5, 1.1, 5.0 / 3.0]) {
+            assert!((discount - expected).abs() < 1e-12, "{} != {}", discount, expected);
+        }
+
+        // No n-gram seen twice.
+        let counts: Counts = [("a".to_owned(), 1), ("b".to_owned(), 3)].into_iter().collect();
+        assert_eq!(Discounts::estimate(&counts), None);
+    }
+
+    #[test]
+    fn probabilities_sum_to_one() {
+        let kn = model(&["the cat sat", "the cat ran", "a dog sat", "the dog sat on the cat"], 3);
+        let vocabulary: Vec<String> = kn.orders[0]
+            .iter()
+            .map(|entry| entry.ngram.clone())
+            .filter(|word| word != BOS)
+            .collect();
+        let lm = BackoffModel::new(kn.orders);
+
+        for context in [vec![], vec![BOS], vec!["the"], vec![BOS, "the"], vec!["the", "cat"], vec!["sat", "on"], vec!["dog", "dog"]] {
+            let sum: f64 = vocabulary
+                .iter()
+                .map(|word| 10f64.powf(lm.log_prob(&context, word)))
+                .sum();
+            assert!((sum - 1.0).abs() < 1e-9, "{:?} sums to {}", context, sum);
+        }
+    }
+}
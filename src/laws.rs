@@ -0,0 +1,135 @@
+// vim:nowrap:
+
+// [Zipf's law](https://en.wikipedia.org/wiki/Zipf%27s_law)
+// [Heaps' law](https://en.wikipedia.org/wiki/Heaps%27_law)
+
+use std::io::{
+    BufRead,
+    Write,
+    self,
+};
+
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+/// Number of types seen after reading a given number of tokens.
+pub type GrowthPoint = (u64, usize);
+
+
+
+/// A power law `y = constant * x^exponent` fitted by least squares in log-log space.
+#[derive(Debug, Clone, Copy)]
+pub struct PowerLaw {
+    pub exponent: f64,
+    pub constant: f64,
+    /// Coefficient of determination of the log-log regression.
+    pub r_squared: f64,
+}
+
+
+
+/// Ordinary least squares on `(ln x, ln y)`.
+/// Returns None if there are fewer than two distinct x values.
+fn fit_log_log<I>(points: I) -> Option<PowerLaw>
+    where
+        I: Iterator<Item=(f64, f64)>,
+{
+    let points: Vec<(f64, f64)> = points
+        .filter(|&(x, y)| x > 0.0 && y > 0.0)
+        .map(|(x, y)| (x.ln(), y.ln()))
+        .collect();
+    let n = points.len() as f64;
+    if points.len() < 2 {
+        return None;
+    }
+
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let syy: f64 = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+    if sxx == 0.0 {
+        return None;
+    }
+
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let r_squared = if syy == 0.0 { 1.0 } else { (sxy * sxy) / (sxx * syy) };
+
+    Some(PowerLaw {
+        exponent: slope,
+        constant: intercept.exp(),
+        r_squared,
+    })
+}
+
+
+
+/// Fit Zipf's law `f(r) = C / r^s` on counts sorted by decreasing frequency.
+/// The returned exponent is `s`, positive for natural language.
+pub fn fit_zipf<I>(sorted_counts: I) -> Option<PowerLaw>
+    where
+        I: Iterator<Item=u32>,
+{
+    fit_log_log(sorted_counts
+        .enumerate()
+        .map(|(rank, count)| ((rank + 1) as f64, count as f64)))
+        .map(|law| PowerLaw { exponent: -law.exponent, ..law })
+}
+
+
+
+/// Fit Heaps' law `V(N) = K * N^beta` on a vocabulary growth curve.
+pub fn fit_heaps(growth: &[GrowthPoint]) -> Option<PowerLaw> {
+    fit_log_log(growth
+        .iter()
+        .map(|&(tokens, types)| (tokens as f64, types as f64)))
+}
+
+
+
+/// Buffer - While - for-loop that also records the vocabulary growth.
+/// A point is recorded every `every` tokens and once more at the end of the input.
+pub fn word_count_with_growth(filename: &Option<String>, every: u64) -> io::Result<(Counts, Vec<GrowthPoint>)> {
+    let every = every.max(1);
+    let mut counts = Counts::new();
+    let mut growth = Vec::new();
+    let mut tokens = 0u64;
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        for word in line.split(char::is_whitespace) {
+            if !word.is_empty() {
+                *counts.entry(word.to_owned()).or_insert(0u32) += 1u32;
+                tokens += 1;
+                if tokens.is_multiple_of(every) {
+                    growth.push((tokens, counts.len()));
+                }
+            }
+        }
+        line.clear();
+    }
+
+    if !tokens.is_multiple_of(every) {
+        growth.push((tokens, counts.len()));
+    }
+
+    Ok((counts, growth))
+}
+
+
+
+/// Write the growth curve as CSV with a `tokens,types` header.
+pub fn write_growth_csv<W: Write>(mut out: W, growth: &[GrowthPoint]) -> io::Result<()> {
+    writeln!(out, "tokens,types")?;
+    for (tokens, types) in growth {
+        writeln!(out, "{},{}", tokens, types)?;
+    }
+
+    out.flush()
+}
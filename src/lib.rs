@@ -49,6 +49,7 @@ use std::io::{
     self,
 };
 use std::path::Path;
+use unicode_segmentation::UnicodeSegmentation;
  
 extern crate regex;
 use regex::Regex;
@@ -56,8 +57,29 @@ use regex::Regex;
 //extern crate Coutner;
 use counter::Counter;
 
-
-type Counts = HashMap<String, u32>;
+pub mod arpa;
+pub mod chars;
+pub mod collocations;
+pub mod cooc;
+pub mod document_frequency;
+pub mod external;
+pub mod filter;
+pub mod freq_of_freq;
+pub mod hyperloglog;
+pub mod jsonl;
+pub mod keywords;
+pub mod kneser_ney;
+pub mod laws;
+pub mod moses;
+pub mod ngrams;
+pub mod parallel;
+pub mod pipeline;
+pub mod space_saving;
+pub mod unkify;
+pub mod vocabulary;
+
+
+pub type Counts = HashMap<String, u32>;
 //type Counts = BTreeMap<String, u32>;   // Almost twice slower than HashMap
 
 
@@ -76,19 +98,28 @@ fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
 
 /// Return an open file or stdin if no filename.
 fn get_reader(filename: &Option<String>) -> Box<dyn BufRead>
+{
+    try_get_reader(filename).unwrap()
+}
+
+
+
+/// Return an open file or stdin if no filename, without panicking if the file can't be opened.
+fn try_get_reader(filename: &Option<String>) -> io::Result<Box<dyn BufRead>>
 {
     // https://stackoverflow.com/a/49964042
     // https://www.reddit.com/r/rust/comments/jv3q3e/comment/gci1mww/?utm_source=share&utm_medium=web2x&context=3
     match filename {
-        None => Box::new(BufReader::new(io::stdin())),
-        Some(filename) if filename == "-"  => Box::new(BufReader::new(io::stdin())),
-        Some(filename) => Box::new(BufReader::new(File::open(filename).unwrap())),
+        None => Ok(Box::new(BufReader::new(io::stdin()))),
+        Some(filename) if filename == "-"  => Ok(Box::new(BufReader::new(io::stdin()))),
+        Some(filename) => Ok(Box::new(BufReader::new(File::open(filename)?))),
     }
 }
 
 
 
 /// Default implementation with for loops.
+#[allow(clippy::len_zero, clippy::manual_flatten)]
 pub fn worc_count_for_for(filename: &Option<String>) -> Counts {
     let mut counts = Counts::new();
 
@@ -111,6 +142,7 @@ pub fn worc_count_for_for(filename: &Option<String>) -> Counts {
 
 
 /// While - for-loop.
+#[allow(clippy::len_zero)]
 pub fn word_count_while_for(filename: &Option<String>) -> Counts {
     // Default implementation with for loops.
     let mut counts = Counts::new();
@@ -134,6 +166,7 @@ pub fn word_count_while_for(filename: &Option<String>) -> Counts {
 
 /// FASTEST OVERALL
 /// Buffer - While - for-loop.
+#[allow(clippy::len_zero)]
 pub fn word_count_buffer_while_for(filename: &Option<String>) -> Counts {
     // Default implementation with for loops.
     let mut counts = Counts::new();
@@ -161,6 +194,7 @@ pub fn word_count_buffer_while_for(filename: &Option<String>) -> Counts {
 
 
 /// for-loop match.
+#[allow(clippy::len_zero)]
 pub fn word_count_for_match_for(filename: &Option<String>) -> Counts {
     let mut counts = Counts::new();
 
@@ -197,7 +231,8 @@ pub fn word_count_for_match_for(filename: &Option<String>) -> Counts {
 /// Using a for-loop and a regular expression.
 pub fn word_count_regex_for(filename: &Option<String>) -> Counts {
     // Example from rosetta code.
-    let word_regex = Regex::new("(?i)[^ ]+").unwrap();
+    // Any whitespace delimits tokens, like the other strategies, not only spaces.
+    let word_regex = Regex::new(r"\S+").unwrap();
 
     let mut words = Counts::new();
     for line in get_reader(filename).lines() {
@@ -434,6 +469,7 @@ pub fn word_count_fluent_5(filename: &Option<String>) -> Counts {
 
 
 ///
+#[allow(clippy::empty_docs)]
 pub fn word_count_fluent_6(filename: &Option<String>) -> Counts {
     // Not sure the result is correct since we are spliting on space and what about newlines?
     let counts = get_reader(filename)
@@ -454,7 +490,8 @@ pub fn word_count_fluent_6(filename: &Option<String>) -> Counts {
 // [Creating a sliding window iterator of slices of chars from a String](https://stackoverflow.com/a/51261570)
 /// Trying not to make copies of the original string but rather have pointer into it for the
 /// substrings.
-fn char_windows<'a>(src: &'a str, win_size: usize) -> impl Iterator<Item = &'a str> {
+#[allow(clippy::needless_lifetimes, clippy::iter_skip_next)]
+pub fn char_windows<'a>(src: &'a str, win_size: usize) -> impl Iterator<Item = &'a str> {
     src.char_indices()
         .flat_map(move |(from, _)| {
             src[from ..].char_indices()
@@ -465,3 +502,19 @@ fn char_windows<'a>(src: &'a str, win_size: usize) -> impl Iterator<Item = &'a s
                 })
     })
 }
+
+
+
+// [UAX #29 Grapheme Cluster Boundaries](https://www.unicode.org/reports/tr29/#Grapheme_Cluster_Boundaries)
+/// Same as `char_windows` but over extended grapheme clusters, so that combining marks, Indic
+/// conjuncts and emoji sequences are never split.
+pub fn grapheme_windows(src: &str, win_size: usize) -> impl Iterator<Item = &str> {
+    src.grapheme_indices(true)
+        .flat_map(move |(from, _)| {
+            src[from ..].grapheme_indices(true)
+                .nth(win_size - 1)
+                .map(|(to, g)| {
+                    &src[from .. from + to + g.len()]
+                })
+    })
+}
@@ -9,11 +9,118 @@
 #![allow(non_camel_case_types)]
 
 use clap::{
+    Args,
     Parser,
     Subcommand,
 };
 use std::cmp::Reverse;
+use std::collections::{
+    BinaryHeap,
+    HashMap,
+    HashSet,
+};
+use std::fs::File;
+use std::io::{
+    BufWriter,
+    Write,
+    self,
+};
+use std::path::PathBuf;
+use std::process;
+use get_voc::arpa::{
+    read_arpa,
+    unigram_model,
+    BackoffModel,
+    write_arpa,
+    Smoothing,
+    UNK,
+};
+use get_voc::chars::{
+    char_count,
+    char_summary,
+    CharInfo,
+    CharUnit,
+};
+use get_voc::collocations::collocations;
+use get_voc::cooc::word_count_cooc;
+use get_voc::document_frequency::{
+    word_count_documents,
+    Documents,
+};
+use get_voc::external::{
+    parse_memory,
+    sort_by_count,
+    word_count_external,
+};
+use get_voc::filter::{
+    filter_lines,
+    filter_pairs,
+    oov_score,
+    FilterOutputs,
+    MooreLewis,
+    OovScore,
+};
+use get_voc::freq_of_freq::{
+    freq_of_freq,
+    good_turing,
+    log_bin,
+    FreqOfFreq,
+};
+use get_voc::hyperloglog::{
+    estimate_types,
+    MAX_PRECISION,
+    MIN_PRECISION,
+};
+use get_voc::keywords::{
+    critical_value,
+    keywords,
+    significance,
+};
+use get_voc::kneser_ney::kneser_ney;
+use get_voc::laws::{
+    fit_heaps,
+    fit_zipf,
+    word_count_with_growth,
+    write_growth_csv,
+    PowerLaw,
+};
+use get_voc::jsonl::JsonPath;
+use get_voc::moses::MosesTokenizer;
+use regex::Regex;
+use get_voc::ngrams::word_count_ngrams;
+use get_voc::parallel::{
+    coverage,
+    token_coverage,
+    word_count_parallel,
+};
+use get_voc::pipeline::{
+    word_count_casings,
+    word_count_pipeline,
+    duplicate_stats,
+    word_count_grouped,
+    word_count_per_field,
+    Case,
+    CharNgrams,
+    Fields,
+    JsonInput,
+    LineUnit,
+    Normalization,
+    Pipeline,
+    PipelineStats,
+    Tokenizer,
+    Unit,
+};
+use get_voc::space_saving::word_count_space_saving;
+use get_voc::unkify::{
+    unkify,
+    Unkifier,
+};
+use get_voc::vocabulary::{
+    read_vocabulary,
+    restrict,
+};
 use get_voc::{
+    Counts,
     worc_count_for_for,
     word_count_while_for,
     word_count_buffer_while_for,
@@ -50,6 +157,140 @@ fn print_counts<I, K, V>(counts: I, show_counts: &bool)
 
 
 
+/// Helper function to write the counts to a file.
+fn write_counts<W, I, K, V>(mut out: W, counts: I, show_counts: &bool) -> io::Result<()>
+    where
+        W: Write,
+        I: Iterator<Item=(K, V)>,
+        K: ::std::fmt::Display,
+        V: ::std::fmt::Display,
+{
+    for (key, value) in counts {
+        if *show_counts {
+            writeln!(out, "{}\t{}", key, value)?;
+        }
+        else {
+            writeln!(out, "{}", key)?;
+        }
+    }
+
+    out.flush()
+}
+
+
+
+/// Accept `\t` for a tab delimiter since it is awkward to type.
+fn parse_delimiter(delimiter: &str) -> Result<char, String> {
+    let mut chars = delimiter.chars();
+    match (delimiter, chars.next(), chars.next()) {
+        ("\\t", _, _) => Ok('\t'),
+        (_, Some(c), None) => Ok(c),
+        _ => Err("must be a single character".to_owned()),
+    }
+}
+
+
+
+/// Sort by decreasing count then alphabetically.
+fn sort_counts(counts: &Counts) -> Vec<(&String, &u32)> {
+    // [Word Frequency](http://rosettacode.org/wiki/Word_frequency#Rust)
+    let mut words: Vec<_> = counts.iter().collect();
+    words.sort_unstable_by_key(|&(word, count)| (Reverse(count), word));
+
+    words
+}
+
+
+
+/// The `topk` best merged external counts sorted like `sort_counts`.
+/// Only the k best entries are ever kept in memory.
+fn external_top<I>(merged: I, topk: usize) -> std::io::Result<Vec<(String, u32)>>
+    where
+        I: Iterator<Item=std::io::Result<(String, u32)>>,
+{
+    // Min-heap on (count, Reverse(word)) keeps the k largest counts.
+    let mut heap = BinaryHeap::with_capacity(topk + 1);
+    for entry in merged {
+        let (word, count) = entry?;
+        heap.push(Reverse((count, Reverse(word))));
+        if heap.len() > topk {
+            heap.pop();
+        }
+    }
+    let mut words: Vec<(String, u32)> = heap.into_iter()
+        .map(|Reverse((count, Reverse(word)))| (word, count))
+        .collect();
+    words.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    Ok(words)
+}
+
+
+
+/// Helper function to display the frequency of frequencies, in logarithmic bins of base `log_bins`.
+fn print_freq_of_freq(fof: &FreqOfFreq, log_bins: Option<f64>) {
+    match log_bins {
+        Some(base) => {
+            for (low, high, types) in log_bin(fof, base) {
+                println!("{}-{}\t{}", low, high, types);
+            }
+        }
+        None => print_counts(fof.iter(), &true),
+    }
+    let gt = good_turing(fof);
+    eprintln!("tokens\t{}", gt.tokens);
+    eprintln!("good_turing_unseen_mass\t{}", gt.unseen_mass);
+}
+
+
+
+/// Unwrap or report the error and exit.
+fn or_exit<T, E: ::std::fmt::Display>(result: Result<T, E>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(2);
+        }
+    }
+}
+
+
+
+/// Helper function to report what the pipeline did.
+fn print_pipeline_stats(stats: &PipelineStats, pipeline: &Pipeline) {
+    eprintln!("lines\t{}", stats.lines);
+    eprintln!("tokens\t{}", stats.tokens);
+    if pipeline.normalization.is_some() {
+        eprintln!("normalized_tokens\t{}", stats.normalized);
+    }
+    if stats.missing_fields > 0 {
+        eprintln!("missing_fields\t{}", stats.missing_fields);
+    }
+    if stats.invalid_json > 0 {
+        eprintln!("invalid_json\t{}", stats.invalid_json);
+    }
+    if stats.invalid_weights > 0 {
+        eprintln!("invalid_weights\t{}", stats.invalid_weights);
+    }
+}
+
+
+
+/// Helper function to display a fitted power law, `title` names it in messages.
+fn print_law(name: &str, title: &str, law: Option<PowerLaw>, exponent: &str, constant: &str) {
+    match law {
+        Some(law) => {
+            println!("{}_{}\t{}", name, exponent, law.exponent);
+            println!("{}_{}\t{}", name, constant, law.constant);
+            println!("{}_r2\t{}", name, law.r_squared);
+        }
+        None => eprintln!("Not enough data to fit {}", title),
+    }
+}
+
+
+
 
 
 #[derive(Parser)]
@@ -65,11 +306,288 @@ struct Cli {
 
     /// topk
     #[clap(short, long, parse(try_from_str))]
-    topk: Option<usize>
+    topk: Option<usize>,
+
+    /// Output the frequency of frequencies `count\tnumber_of_types` instead of the vocabulary
+    #[clap(long)]
+    freq_of_freq: bool,
+
+    /// Group the frequency of frequencies in logarithmic bins of this base
+    #[clap(long, name="base", parse(try_from_str), requires="freq-of-freq", validator=valid_base)]
+    log_bins: Option<f64>,
+
+    /// Only estimate the number of types using HyperLogLog in constant memory
+    #[clap(long)]
+    estimate_types: bool,
+
+    /// HyperLogLog precision, uses 2^precision registers
+    #[clap(long, default_value="14", parse(try_from_str), validator=valid_precision)]
+    precision: u8,
+}
+
+
+
+fn valid_precision(precision: &str) -> Result<(), String> {
+    match precision.parse::<u8>() {
+        Ok(p) if (MIN_PRECISION..=MAX_PRECISION).contains(&p) => Ok(()),
+        _ => Err(format!("must be an integer between {} and {}", MIN_PRECISION, MAX_PRECISION)),
+    }
 }
 
 
 
+fn valid_positive(value: &str) -> Result<(), String> {
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err("must be a positive integer".to_owned()),
+    }
+}
+
+
+
+fn valid_positive_number(value: &str) -> Result<(), String> {
+    match value.parse::<f64>() {
+        Ok(x) if x > 0.0 && x.is_finite() => Ok(()),
+        _ => Err("must be a positive number".to_owned()),
+    }
+}
+
+
+
+fn valid_base(base: &str) -> Result<(), String> {
+    match base.parse::<f64>() {
+        Ok(b) if b > 1.0 && b.is_finite() => Ok(()),
+        _ => Err("must be a number greater than 1".to_owned()),
+    }
+}
+
+
+
+fn valid_probability(probability: &str) -> Result<(), String> {
+    match probability.parse::<f64>() {
+        Ok(p) if p > 0.0 && p < 1.0 => Ok(()),
+        _ => Err("must be a number in (0, 1)".to_owned()),
+    }
+}
+
+
+
+/// Helper function to create an output file, or stdout without a filename.
+fn create(filename: Option<&str>) -> Result<Box<dyn Write>, String> {
+    match filename {
+        Some(filename) => File::create(filename)
+            .map(|file| Box::new(BufWriter::new(file)) as Box<dyn Write>)
+            .map_err(|e| format!("Can't create {}: {}", filename, e)),
+        None => Ok(Box::new(BufWriter::new(io::stdout()))),
+    }
+}
+
+
+
+/// Helper function to build the in-domain and general models from vocabularies, corpora or ARPA
+/// files.
+/// Add-one unigram models of vocabularies share the in-domain tokens seen at least `min_count`
+/// times, the other tokens count as <unk>, and don't score </s> since they have no sentence counts.
+fn moore_lewis(in_domain: &str, general: &str, models: &str, order: usize, min_count: u32, pipeline: &Pipeline) -> Result<MooreLewis, String> {
+    fn read_error(filename: &str) -> impl Fn(io::Error) -> String + '_ {
+        move |e| format!("Can't read {}: {}", filename, e)
+    }
+    let moore_lewis = match models {
+        "arpa" => MooreLewis {
+            in_domain: read_arpa(&Some(in_domain.to_owned())).map_err(read_error(in_domain))?,
+            general: read_arpa(&Some(general.to_owned())).map_err(read_error(general))?,
+            eos: true,
+        },
+        "corpus" => {
+            let model = |filename: &str| -> Result<BackoffModel, String> {
+                let (counts, _stats) = word_count_ngrams(&Some(filename.to_owned()), pipeline, order, true).map_err(read_error(filename))?;
+                Ok(BackoffModel::new(kneser_ney(&counts).orders))
+            };
+            MooreLewis {
+                in_domain: model(in_domain)?,
+                general: model(general)?,
+                eos: true,
+            }
+        }
+        _ => {
+            let in_domain = read_vocabulary(&Some(in_domain.to_owned())).map_err(read_error(in_domain))?;
+            let general = read_vocabulary(&Some(general.to_owned())).map_err(read_error(general))?;
+            let vocabulary: HashSet<&String> = in_domain
+                .iter()
+                .filter(|&(_, &count)| count >= min_count)
+                .map(|(token, _)| token)
+                .collect();
+            let model = |counts: &Counts| unigram_model(&restrict(counts, &vocabulary, UNK), 0, Smoothing::AddK(1.0), None).map(|model| BackoffModel::new(vec![model]));
+            MooreLewis {
+                in_domain: model(&in_domain)?,
+                general: model(&general)?,
+                eos: false,
+            }
+        }
+    };
+    Ok(moore_lewis)
+}
+
+
+
+/// Options of the shared tokenizer pipeline.
+#[derive(Args)]
+struct PipelineArgs {
+    /// Parse lines as json and count the text at this dotted path, e.g. text or doc.body
+    #[clap(long, name="PATH", parse(try_from_str), conflicts_with="field")]
+    json_field: Option<JsonPath>,
+
+    /// Count a separate vocabulary for each value at this dotted json path, e.g. lang
+    #[clap(long, parse(try_from_str), requires="PATH")]
+    group_by: Option<JsonPath>,
+
+    /// Only tokenize these fields of delimited lines, numbered from 1, e.g. --field 2 or --field 1,3
+    #[clap(long, parse(try_from_str), use_value_delimiter=true, multiple_values=false, validator=valid_positive)]
+    field: Vec<usize>,
+
+    /// Field delimiter
+    #[clap(long, default_value="\\t", parse(try_from_str=parse_delimiter))]
+    delimiter: char,
+
+    /// Count each line as many times as the number in this field, numbered from 1, e.g. 1 for `uniq -c` output with --delimiter ' ', with count, collocations, lm and the --corpus of arpa and keywords
+    #[clap(long, parse(try_from_str), conflicts_with="PATH", validator=valid_positive)]
+    weight_field: Option<usize>,
+
+    /// How to split lines into tokens
+    #[clap(long, default_value="whitespace", possible_values=["whitespace", "moses"])]
+    tokenizer: String,
+
+    /// Tokens are the matches of this regex, e.g. '\w+' or '\p{Alphabetic}+'
+    #[clap(long, parse(try_from_str), conflicts_with_all=&["tokenizer", "split-regex"])]
+    token_regex: Option<Regex>,
+
+    /// Split lines on the matches of this regex, e.g. '[\s,;]+'
+    #[clap(long, parse(try_from_str), conflicts_with="tokenizer")]
+    split_regex: Option<Regex>,
+
+    /// Language of the moses tokenizer
+    #[clap(long, default_value="en")]
+    lang: String,
+
+    /// Moses nonbreaking_prefix file replacing the builtin prefixes of --lang
+    #[clap(long)]
+    nonbreaking_prefixes: Option<String>,
+
+    /// Moses aggressive hyphen splitting, `a-b` becomes `a @-@ b`
+    #[clap(long)]
+    aggressive_hyphen: bool,
+
+    /// Don't escape special characters with the moses tokenizer
+    #[clap(long)]
+    no_escape: bool,
+
+    /// Unicode normalization applied to each line or field before tokenization
+    #[clap(long, possible_values=["nfc", "nfd", "nfkc", "nfkd"], parse(try_from_str))]
+    normalize: Option<Normalization>,
+
+    /// Lowercase tokens
+    #[clap(long, conflicts_with="casefold")]
+    lowercase: bool,
+
+    /// Apply full unicode case folding to tokens
+    #[clap(long)]
+    casefold: bool,
+
+    /// Count tokens or whole lines, made of the selected fields without the weight field
+    #[clap(long, default_value="token", possible_values=["token", "line"])]
+    unit: String,
+
+    /// Trim and squeeze whitespace of line units
+    #[clap(long)]
+    trim: bool,
+
+    /// Replace line units by a 64 bit hash to save memory
+    #[clap(long)]
+    hash_lines: bool,
+
+    /// Replace each token by its character n-grams of this order, 1 for a character vocabulary
+    #[clap(long, parse(try_from_str), conflicts_with="hash-lines")]
+    char_ngram: Option<usize>,
+
+    /// Characters are extended grapheme clusters, keeping combining marks and emoji sequences whole
+    #[clap(long, requires="char-ngram")]
+    graphemes: bool,
+}
+
+
+
+impl PipelineArgs {
+    fn pipeline(&self) -> Result<Pipeline, String> {
+        let tokenizer = match (&self.token_regex, &self.split_regex, self.tokenizer.as_str()) {
+            (Some(regex), _, _) => Tokenizer::Pattern(regex.clone()),
+            (_, Some(regex), _) => Tokenizer::Split(regex.clone()),
+            (_, _, "moses") => {
+                let mut moses = MosesTokenizer::new(&self.lang);
+                moses.aggressive_hyphen = self.aggressive_hyphen;
+                moses.escape = !self.no_escape;
+                if let Some(prefixes) = &self.nonbreaking_prefixes {
+                    moses
+                        .load_nonbreaking_prefixes(prefixes)
+                        .map_err(|e| format!("Can't read {}: {}", prefixes, e))?;
+                }
+                Tokenizer::Moses(Box::new(moses))
+            }
+            _ => Tokenizer::Whitespace,
+        };
+
+        let case = if self.casefold {
+            Some(Case::Fold)
+        }
+        else if self.lowercase {
+            Some(Case::Lower)
+        }
+        else {
+            None
+        };
+
+        let fields = if self.field.is_empty() {
+            Fields::Line
+        }
+        else {
+            Fields::Select(self.field.clone())
+        };
+
+        let char_ngrams = match self.char_ngram {
+            Some(0) => return Err("--char-ngram must be at least 1".to_owned()),
+            Some(order) => Some(CharNgrams {
+                order,
+                unit: if self.graphemes { CharUnit::Grapheme } else { CharUnit::CodePoint },
+            }),
+            None => None,
+        };
+
+        let json = self.json_field.clone().map(|text| JsonInput {
+            text,
+            group_by: self.group_by.clone(),
+        });
+
+        Ok(Pipeline {
+            json,
+            fields,
+            delimiter: self.delimiter,
+            weight_field: self.weight_field,
+            tokenizer,
+            normalization: self.normalize,
+            case,
+            char_ngrams,
+            unit: if self.unit == "line" { Unit::Line } else { Unit::Token },
+            line_unit: LineUnit {
+                trim: self.trim,
+                hash: self.hash_lines,
+            },
+        })
+    }
+}
+
+
+
+// Parsed once, the size of the variants doesn't matter.
+#[allow(clippy::large_enum_variant)]
 #[derive(Subcommand)]
 enum Commands {
     #[clap(arg_required_else_help=false)]
@@ -159,6 +677,483 @@ enum Commands {
         #[clap(name="Input file")]
         filename: Option<String>,
     },
+
+    #[clap(arg_required_else_help=false)]
+    /// Fit Zipf's and Heaps' laws.
+    laws {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Write the vocabulary growth curve as CSV to this file
+        #[clap(long, name="growth.csv")]
+        growth: Option<String>,
+
+        /// Record a growth point every N tokens
+        #[clap(long, default_value="1000", parse(try_from_str))]
+        every: u64,
+    },
+
+    #[clap(arg_required_else_help=false, visible_alias="approx")]
+    /// Approximate top-k in bounded memory using Space-Saving.
+    /// With --show-counts, prints the estimated count and its maximum overestimation.
+    space_saving {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Maximum number of monitored tokens
+        #[clap(long, default_value="100000", parse(try_from_str))]
+        capacity: usize,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Count through the configurable tokenizer pipeline.
+    count {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+
+        /// Output the surface casings seen for each caseless type, `type\tcount\tcasing:count ...`
+        #[clap(long, conflicts_with_all=&["char-ngram", "hash-lines", "group-by"])]
+        casings: bool,
+
+        /// Count word n-grams of this order
+        #[clap(long, default_value="1", parse(try_from_str), conflicts_with_all=&["casings", "group-by"], validator=valid_positive)]
+        ngram: usize,
+
+        /// Count each field separately and write their vocabularies to PREFIX.<field>
+        #[clap(long, name="PREFIX", conflicts_with_all=&["casings", "PATH"])]
+        per_field: Option<String>,
+    },
+
+    #[clap(arg_required_else_help=false, visible_alias="df")]
+    /// Document frequencies, prints `token\tcount\tdf\tidf\ttfidf`.
+    /// Use --json-field to make each JSONL record a document.
+    document_frequency {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// What makes a document
+        #[clap(long, default_value="lines", possible_values=["lines", "paragraphs"])]
+        documents: String,
+
+        /// Sort by decreasing value of
+        #[clap(long, default_value="count", possible_values=["count", "df", "tfidf"])]
+        sort_by: String,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Bigram collocations, prints `bigram\tcount\tpmi\tnpmi\tt_score\tlog_likelihood`.
+    collocations {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Minimum bigram count
+        #[clap(long, default_value="5", parse(try_from_str))]
+        min_count: u32,
+
+        /// Minimum count of both tokens of a bigram
+        #[clap(long, default_value="1", parse(try_from_str))]
+        min_unigram_count: u32,
+
+        /// Sort by decreasing value of
+        #[clap(long, default_value="ll", possible_values=["pmi", "npmi", "tscore", "ll"])]
+        sort_by: String,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=true)]
+    /// Keywords of a target compared to a reference, prints
+    /// `token\ttarget_count\treference_count\tlog_likelihood\tchi_square\tlog_ratio\tsignificance`.
+    keywords {
+        /// Target vocabulary, `token\tcount`
+        target: String,
+
+        /// Reference vocabulary, `token\tcount`
+        reference: String,
+
+        /// The inputs are corpora to count with the pipeline instead of vocabularies
+        #[clap(long)]
+        corpus: bool,
+
+        /// Significance level of the log-likelihood
+        #[clap(long, default_value="0.05", possible_values=["0.05", "0.01", "0.001", "0.0001"], parse(try_from_str))]
+        p: f64,
+
+        /// Minimum count in either corpus
+        #[clap(long, default_value="5", parse(try_from_str))]
+        min_count: u32,
+
+        /// Also list the tokens underused in the target
+        #[clap(long)]
+        underused: bool,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Unigram language model of a vocabulary in ARPA format.
+    /// With --topk, the other tokens are counted as <unk>.
+    arpa {
+        /// Input vocabulary, `token\tcount`
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// The input is a corpus to count with the pipeline instead of a vocabulary
+        #[clap(long)]
+        corpus: bool,
+
+        #[clap(long, default_value="good-turing", possible_values=["add-k", "good-turing"])]
+        smoothing: String,
+
+        /// Constant added to every count with add-k smoothing
+        #[clap(long, default_value="1", parse(try_from_str), validator=valid_positive_number)]
+        k: f64,
+
+        /// Probability of <unk> instead of the mass reserved by the smoothing
+        #[clap(long, parse(try_from_str), validator=valid_probability)]
+        unk_mass: Option<f64>,
+
+        /// Number of sentences for the probability of </s>, defaults to the number of lines with --corpus
+        #[clap(long, parse(try_from_str))]
+        sentences: Option<u32>,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Interpolated modified Kneser-Ney language model of a corpus in ARPA format.
+    lm {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Highest n-gram order
+        #[clap(long, default_value="3", parse(try_from_str), validator=valid_positive)]
+        order: usize,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=true)]
+    /// Moore-Lewis filtering, keeps the lines whose in-domain minus general cross-entropy is below
+    /// the threshold.
+    /// The scores are `kept|rejected\tdifference\tin_domain\tgeneral` per line.
+    filter {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// In-domain model
+        #[clap(long)]
+        in_domain: String,
+
+        /// General model
+        #[clap(long)]
+        general: String,
+
+        /// The models are add-one unigram models of vocabularies, Kneser-Ney models of corpora
+        /// or ARPA files
+        #[clap(long, default_value="vocabulary", possible_values=["vocabulary", "corpus", "arpa"])]
+        models: String,
+
+        /// Order of the models of corpora
+        #[clap(long, default_value="1", parse(try_from_str), validator=valid_positive)]
+        order: usize,
+
+        /// Vocabulary of the models of vocabularies, the in-domain tokens seen at least this many times
+        #[clap(long, default_value="2", parse(try_from_str))]
+        min_count: u32,
+
+        /// Keep the lines scoring at most this
+        #[clap(long, default_value="0", parse(try_from_str), allow_hyphen_values=true)]
+        threshold: f64,
+
+        /// Write the kept lines to this file instead of stdout
+        #[clap(long)]
+        kept: Option<String>,
+
+        /// Write the rejected lines to this file
+        #[clap(long)]
+        rejected: Option<String>,
+
+        /// Write the scores of each line to this file
+        #[clap(long)]
+        scores: Option<String>,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=true)]
+    /// Keeps the lines with few rare or unknown tokens.
+    /// With --target, the lines of both sentence aligned files are kept together if both pass.
+    /// The scores are `kept|rejected\toov_ratio\tmean_log_frequency` per line, followed by those of
+    /// the target.
+    filter_oov {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Vocabulary, `token\tcount`
+        #[clap(long)]
+        vocabulary: String,
+
+        /// Tokens seen less than this many times in the vocabulary are OOV
+        #[clap(long, default_value="1", parse(try_from_str))]
+        min_count: u32,
+
+        /// Keep the lines with at most this fraction of OOV tokens
+        #[clap(long, default_value="0.1", parse(try_from_str))]
+        max_oov_ratio: f64,
+
+        /// Keep the lines whose mean log10(1 + count) is at least this
+        #[clap(long, default_value="0", parse(try_from_str))]
+        min_mean_log_frequency: f64,
+
+        /// Write the kept lines to this file instead of stdout
+        #[clap(long)]
+        kept: Option<String>,
+
+        /// Write the rejected lines to this file
+        #[clap(long)]
+        rejected: Option<String>,
+
+        /// Write the scores of each line to this file
+        #[clap(long)]
+        scores: Option<String>,
+
+        /// Target file sentence aligned with the input file
+        #[clap(long, requires_all=&["target-vocabulary", "kept-target", "Input file"])]
+        target: Option<String>,
+
+        /// Vocabulary of the target file
+        #[clap(long, requires="target")]
+        target_vocabulary: Option<String>,
+
+        /// Write the kept target lines to this file
+        #[clap(long, requires="target")]
+        kept_target: Option<String>,
+
+        /// Write the rejected target lines to this file
+        #[clap(long, requires="target")]
+        rejected_target: Option<String>,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Rewrites the tokens of each line replacing the out-of-vocabulary ones with <unk>.
+    /// Without --vocabulary, the input file is read twice, first to count its vocabulary.
+    /// With --topk, only the topk most frequent tokens are in the vocabulary.
+    unkify {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Vocabulary, `token\tcount`, instead of counting the input file
+        #[clap(long)]
+        vocabulary: Option<String>,
+
+        /// Tokens seen less than this many times are out-of-vocabulary
+        #[clap(long, default_value="2", parse(try_from_str))]
+        min_count: u32,
+
+        /// Use <unk-num>, <unk-punct> and <unk-cap> for unknown numbers, punctuation and capitalized tokens
+        #[clap(long)]
+        classes: bool,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=true)]
+    /// Source, target and joint vocabularies of sentence aligned files with their coverage.
+    /// With --topk, also reports the coverage of each side by the joint topk vocabulary.
+    parallel {
+        /// Source file
+        source: String,
+
+        /// Target file
+        target: String,
+
+        /// Write the vocabularies to PREFIX.source, PREFIX.target and PREFIX.joint
+        #[clap(long)]
+        prefix: Option<String>,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=true)]
+    /// Sentence co-occurrences of source and target words of sentence aligned files, prints
+    /// `source\ttarget\tcount\tsource_df\ttarget_df\tdice\tpmi` grouped by source word.
+    cooc {
+        /// Source file
+        source: String,
+
+        /// Target file
+        target: String,
+
+        /// Minimum number of sentences a word must appear in
+        #[clap(long, default_value="2", parse(try_from_str))]
+        min_word_count: u32,
+
+        /// Minimum number of sentence pairs a pair must appear in
+        #[clap(long, default_value="2", parse(try_from_str))]
+        min_count: u32,
+
+        /// Keep only the best N target words of each source word
+        #[clap(long, name="N", parse(try_from_str))]
+        per_source: Option<usize>,
+
+        /// Prune the least frequent pairs whenever there are more than this many
+        #[clap(long, parse(try_from_str))]
+        max_pairs: Option<usize>,
+
+        /// Rank the target words of a source word by
+        #[clap(long, default_value="dice", possible_values=["dice", "pmi", "count"])]
+        sort_by: String,
+
+        #[clap(flatten)]
+        pipeline: PipelineArgs,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Character inventory, prints `char\tcount\tcode_points\tcategory\tscript\tname\tflags`.
+    /// Control, private-use and replacement characters are flagged.
+    chars {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Count code points or extended grapheme clusters
+        #[clap(long, default_value="code-point", possible_values=["code-point", "grapheme"])]
+        unit: String,
+    },
+
+    #[clap(arg_required_else_help=false)]
+    /// Exact counting within a memory budget by spilling sorted partial counts to disk, then
+    /// sorting the vocabulary by count the same way unless only the --topk best are needed.
+    external {
+        /// Input file
+        #[clap(name="Input file")]
+        filename: Option<String>,
+
+        /// Approximate memory budget for the counts, e.g. 512M or 2G
+        #[clap(long, default_value="1G", parse(try_from_str=parse_memory))]
+        max_memory: usize,
+
+        /// Directory for the temporary files
+        #[clap(long, parse(from_os_str))]
+        tmp_dir: Option<PathBuf>,
+    },
+}
+
+
+
+impl Commands {
+    /// Tokenizer pipeline options of the subcommands that have them.
+    fn pipeline_args(&self) -> Option<&PipelineArgs> {
+        match self {
+            Commands::count {pipeline, ..}
+            | Commands::document_frequency {pipeline, ..}
+            | Commands::collocations {pipeline, ..}
+            | Commands::keywords {pipeline, ..}
+            | Commands::arpa {pipeline, ..}
+            | Commands::lm {pipeline, ..}
+            | Commands::filter {pipeline, ..}
+            | Commands::filter_oov {pipeline, ..}
+            | Commands::unkify {pipeline, ..}
+            | Commands::parallel {pipeline, ..}
+            | Commands::cooc {pipeline, ..} => Some(pipeline),
+            _ => None,
+        }
+    }
+
+    /// Pipeline option the subcommand would silently ignore.
+    fn ignored_pipeline_arg(&self) -> Option<&'static str> {
+        let pipeline = self.pipeline_args()?;
+        match self {
+            // count weighs lines and has one vocabulary per group.
+            Commands::count {..} => None,
+            _ if pipeline.group_by.is_some() => Some("--group-by"),
+            // The other counting subcommands weigh lines but have a single vocabulary.
+            Commands::collocations {..}
+            | Commands::lm {..}
+            | Commands::arpa {corpus: true, ..}
+            | Commands::keywords {corpus: true, ..} => None,
+            _ if pipeline.weight_field.is_some() => Some("--weight-field"),
+            _ => None,
+        }
+    }
+
+    /// Whether the subcommand counts a single vocabulary, which `--freq-of-freq` can summarize.
+    fn counts_vocabulary(&self) -> bool {
+        match self {
+            Commands::count {per_field, casings, pipeline, ..} => per_field.is_none() && !casings && pipeline.group_by.is_none(),
+            Commands::wcff {..}
+            | Commands::wcwf {..}
+            | Commands::wcbwf {..}
+            | Commands::wcfmf {..}
+            | Commands::wcrf {..}
+            | Commands::wc_f1 {..}
+            | Commands::wc_f2 {..}
+            | Commands::wc_f3 {..}
+            | Commands::wc_f4 {..}
+            | Commands::wc_f5 {..}
+            | Commands::wc_f6 {..}
+            | Commands::external {..} => true,
+            _ => false,
+        }
+    }
+
+    /// Input file of the subcommands reading a single file.
+    fn filename(&self) -> Option<&Option<String>> {
+        match self {
+            Commands::wcff {filename}
+            | Commands::wcwf {filename}
+            | Commands::wcbwf {filename}
+            | Commands::wcfmf {filename}
+            | Commands::wcrf {filename}
+            | Commands::wc_f1 {filename}
+            | Commands::wc_f2 {filename}
+            | Commands::wc_f3 {filename}
+            | Commands::wc_f4 {filename}
+            | Commands::wc_f5 {filename}
+            | Commands::wc_f6 {filename}
+            | Commands::laws {filename, ..}
+            | Commands::space_saving {filename, ..}
+            | Commands::count {filename, ..}
+            | Commands::document_frequency {filename, ..}
+            | Commands::collocations {filename, ..}
+            | Commands::arpa {filename, ..}
+            | Commands::lm {filename, ..}
+            | Commands::filter {filename, ..}
+            | Commands::filter_oov {filename, ..}
+            | Commands::unkify {filename, ..}
+            | Commands::chars {filename, ..}
+            | Commands::external {filename, ..} => Some(filename),
+            Commands::parallel {..}
+            | Commands::cooc {..}
+            | Commands::keywords {..} => None,
+        }
+    }
 }
 
 
@@ -167,6 +1162,32 @@ enum Commands {
 
 fn main() {
     let args = Cli::parse();
+
+    if let Some(arg) = args.command.ignored_pipeline_arg() {
+        or_exit(Err(format!("{} isn't supported by this subcommand", arg)))
+    }
+
+    if args.estimate_types {
+        let filename = or_exit(args.command.filename().ok_or("--estimate-types needs a single input file"));
+        let pipeline = match args.command.pipeline_args() {
+            Some(pipeline) => or_exit(pipeline.pipeline()),
+            None => Pipeline::default(),
+        };
+        let (hll, stats) = or_exit(estimate_types(filename, &pipeline, args.precision));
+        let estimate = hll.estimate();
+        let error = hll.standard_error();
+        println!("estimated_types\t{:.0}", estimate);
+        println!("precision\t{}", hll.precision());
+        println!("standard_error\t{:.4}", error);
+        println!("95%_interval\t{:.0}\t{:.0}", estimate * (1.0 - 2.0 * error), estimate * (1.0 + 2.0 * error));
+        print_pipeline_stats(&stats, &pipeline);
+        return;
+    }
+
+    if args.freq_of_freq && !args.command.counts_vocabulary() {
+        or_exit(Err("--freq-of-freq needs a subcommand counting a single vocabulary"))
+    }
+
     let counts = match &args.command {
         Commands::wcff {filename} => worc_count_for_for(filename),
         Commands::wcwf {filename} => word_count_while_for(filename),
@@ -179,11 +1200,438 @@ fn main() {
         Commands::wc_f4 {filename} => word_count_fluent_4_map_reduce(filename),
         Commands::wc_f5 {filename} => word_count_fluent_5(filename),
         Commands::wc_f6 {filename} => word_count_fluent_6(filename),
+        Commands::laws {filename, growth: growth_csv, every} => {
+            let (counts, growth) = or_exit(word_count_with_growth(filename, *every));
+            let words = sort_counts(&counts);
+            print_law("zipf", "Zipf's law", fit_zipf(words.iter().map(|&(_, &count)| count)), "exponent", "constant");
+            print_law("heaps", "Heaps' law", fit_heaps(&growth), "beta", "k");
+            if let Some(growth_csv) = growth_csv {
+                or_exit(write_growth_csv(or_exit(create(Some(growth_csv))), &growth));
+            }
+            return;
+        }
+        Commands::space_saving {filename, capacity} => {
+            let summary = or_exit(word_count_space_saving(filename, *capacity));
+            let top = summary.top();
+            let top = top.into_iter().take(args.topk.unwrap_or(usize::MAX));
+            if args.show_counts {
+                for (word, estimate) in top {
+                    println!("{}\t{}\t{}", word, estimate.count, estimate.error);
+                }
+            }
+            else {
+                for (word, _) in top {
+                    println!("{}", word);
+                }
+            }
+            eprintln!("tokens\t{}", summary.tokens());
+            eprintln!("unmonitored_max_count\t{}", summary.min_count());
+            return;
+        }
+        Commands::count {filename, pipeline, per_field: Some(prefix), ..} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_per_field(filename, &pipeline));
+            print_pipeline_stats(&stats, &pipeline);
+            for (field, counts) in counts {
+                let words = sort_counts(&counts);
+                let filename = format!("{}.{}", prefix, field);
+                let file = or_exit(File::create(&filename).map_err(|e| format!("Can't create {}: {}", filename, e)));
+                let words = words.into_iter().take(args.topk.unwrap_or(usize::MAX));
+                or_exit(write_counts(BufWriter::new(file), words, &args.show_counts));
+                eprintln!("field_{}_types\t{}", field, counts.len());
+            }
+            return;
+        }
+        Commands::count {filename, pipeline, ..} if pipeline.group_by.is_some() => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_grouped(filename, &pipeline));
+            print_pipeline_stats(&stats, &pipeline);
+            for (group, counts) in counts {
+                let words = sort_counts(&counts);
+                let words = words
+                    .into_iter()
+                    .take(args.topk.unwrap_or(usize::MAX))
+                    .map(|(word, count)| (format!("{}\t{}", group, word), count));
+                print_counts(words, &args.show_counts);
+            }
+            return;
+        }
+        Commands::count {filename, pipeline, casings: true, ..} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_casings(filename, &pipeline));
+            print_pipeline_stats(&stats, &pipeline);
+            let mut entries: Vec<_> = counts.iter().collect();
+            entries.sort_unstable_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+            for (caseless, entry) in entries.into_iter().take(args.topk.unwrap_or(usize::MAX)) {
+                let casings: Vec<String> = entry
+                    .sorted_casings()
+                    .into_iter()
+                    .map(|(casing, count)| format!("{}:{}", casing, count))
+                    .collect();
+                println!("{}\t{}\t{}", caseless, entry.count, casings.join(" "));
+            }
+            return;
+        }
+        Commands::count {filename, pipeline, ngram, ..} if *ngram > 1 => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (mut counts, stats) = or_exit(word_count_ngrams(filename, &pipeline, *ngram, false));
+            print_pipeline_stats(&stats, &pipeline);
+            counts.orders.swap_remove(ngram - 1)
+        }
+        Commands::count {filename, pipeline, ..} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_pipeline(filename, &pipeline));
+            print_pipeline_stats(&stats, &pipeline);
+            if pipeline.unit == Unit::Line && pipeline.char_ngrams.is_none() {
+                let duplicates = duplicate_stats(&counts);
+                eprintln!("unique_lines\t{}", duplicates.unique_lines);
+                eprintln!("repeated_lines\t{}", duplicates.repeated_lines);
+                eprintln!("duplicates\t{}", duplicates.duplicates);
+                eprintln!("duplicate_rate\t{:.6}", duplicates.duplicate_rate());
+            }
+            counts
+        }
+        Commands::document_frequency {filename, documents, sort_by, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let documents = if documents == "paragraphs" { Documents::Paragraphs } else { Documents::Lines };
+            let (counts, stats) = or_exit(word_count_documents(filename, &pipeline, documents));
+            print_pipeline_stats(&stats, &pipeline);
+            eprintln!("documents\t{}", counts.documents);
+
+            let n = counts.documents;
+            let mut terms: Vec<_> = counts.terms.iter().collect();
+            match sort_by.as_str() {
+                "df" => terms.sort_unstable_by(|a, b| b.1.df.cmp(&a.1.df).then(a.0.cmp(b.0))),
+                "tfidf" => terms.sort_unstable_by(|a, b| b.1.tf_idf(n).total_cmp(&a.1.tf_idf(n)).then(a.0.cmp(b.0))),
+                _ => terms.sort_unstable_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0))),
+            }
+            for (token, term) in terms.into_iter().take(args.topk.unwrap_or(usize::MAX)) {
+                println!("{}\t{}\t{}\t{:.6}\t{:.6}", token, term.count, term.df, term.idf(n), term.tf_idf(n));
+            }
+            return;
+        }
+        Commands::collocations {filename, min_count, min_unigram_count, sort_by, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_ngrams(filename, &pipeline, 2, false));
+            print_pipeline_stats(&stats, &pipeline);
+
+            let mut collocations = collocations(&counts, *min_count, *min_unigram_count);
+            let score = |association: &get_voc::collocations::Association| match sort_by.as_str() {
+                "pmi" => association.pmi,
+                "npmi" => association.npmi,
+                "tscore" => association.t_score,
+                _ => association.log_likelihood,
+            };
+            collocations.sort_unstable_by(|a, b| {
+                score(&b.association)
+                    .total_cmp(&score(&a.association))
+                    .then(a.bigram.cmp(b.bigram))
+            });
+            for collocation in collocations.into_iter().take(args.topk.unwrap_or(usize::MAX)) {
+                println!("{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{:.4}",
+                    collocation.bigram,
+                    collocation.count,
+                    collocation.association.pmi,
+                    collocation.association.npmi,
+                    collocation.association.t_score,
+                    collocation.association.log_likelihood);
+            }
+            return;
+        }
+        Commands::keywords {target, reference, corpus, p, min_count, underused, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let load = |filename: &String| {
+                if *corpus {
+                    or_exit(word_count_pipeline(&Some(filename.clone()), &pipeline).map_err(|e| format!("Can't read {}: {}", filename, e))).0
+                }
+                else {
+                    or_exit(read_vocabulary(&Some(filename.clone())).map_err(|e| format!("Can't read {}: {}", filename, e)))
+                }
+            };
+            let target = load(target);
+            let reference = load(reference);
+
+            let threshold = critical_value(*p).expect("validated by clap");
+            let keywords = keywords(&target, &reference, *min_count, *underused);
+            let keywords = keywords
+                .into_iter()
+                .filter(|keyword| keyword.keyness.log_likelihood >= threshold)
+                .take(args.topk.unwrap_or(usize::MAX));
+            for keyword in keywords {
+                println!("{}\t{}\t{}\t{:.4}\t{:.4}\t{:.4}\t{}",
+                    keyword.token,
+                    keyword.target,
+                    keyword.reference,
+                    keyword.keyness.log_likelihood,
+                    keyword.keyness.chi_square,
+                    keyword.keyness.log_ratio,
+                    significance(keyword.keyness.log_likelihood));
+            }
+            return;
+        }
+        Commands::arpa {filename, corpus, smoothing, k, unk_mass, sentences, pipeline} => {
+            let (counts, lines) = if *corpus {
+                let pipeline = or_exit(pipeline.pipeline());
+                let (counts, stats) = or_exit(word_count_pipeline(filename, &pipeline));
+                print_pipeline_stats(&stats, &pipeline);
+                (counts, stats.lines)
+            }
+            else {
+                (or_exit(read_vocabulary(filename).map_err(|e| format!("Can't read the vocabulary: {}", e))), 0)
+            };
+            let counts = match args.topk {
+                Some(topk) => {
+                    let mut vocabulary: Counts = HashMap::new();
+                    for (i, (word, &count)) in sort_counts(&counts).into_iter().enumerate() {
+                        let word = if i < topk { word.clone() } else { UNK.to_owned() };
+                        *vocabulary.entry(word).or_insert(0u32) += count;
+                    }
+                    vocabulary
+                }
+                None => counts,
+            };
+
+            let smoothing = if smoothing == "add-k" { Smoothing::AddK(*k) } else { Smoothing::GoodTuring };
+            let sentences = match sentences {
+                Some(sentences) => *sentences,
+                None => or_exit(u32::try_from(lines).map_err(|_| format!("too many lines to count </s>: {}", lines))),
+            };
+            let model = or_exit(unigram_model(&counts, sentences, smoothing, *unk_mass));
+            let stdout = io::stdout();
+            or_exit(write_arpa(BufWriter::new(stdout.lock()), &[model]));
+            return;
+        }
+        Commands::lm {filename, order, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_ngrams(filename, &pipeline, *order, true));
+            print_pipeline_stats(&stats, &pipeline);
+
+            let model = kneser_ney(&counts);
+            for (n, (discounts, fallback)) in model.discounts.iter().zip(&model.fallback).enumerate() {
+                eprintln!("discounts_{}\t{:.4}\t{:.4}\t{:.4}{}",
+                    n + 1,
+                    discounts.0[0],
+                    discounts.0[1],
+                    discounts.0[2],
+                    if *fallback { "\tfallback" } else { "" });
+            }
+            let stdout = io::stdout();
+            or_exit(write_arpa(BufWriter::new(stdout.lock()), &model.orders));
+            return;
+        }
+        Commands::filter {filename, in_domain, general, models, order, min_count, threshold, kept, rejected, scores, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let moore_lewis = or_exit(moore_lewis(in_domain, general, models, *order, *min_count, &pipeline));
+            let mut outputs = FilterOutputs {
+                kept: or_exit(create(kept.as_deref())),
+                rejected: rejected.as_deref().map(|rejected| or_exit(create(Some(rejected)))),
+                scores: scores.as_deref().map(|scores| or_exit(create(Some(scores)))),
+            };
+
+            let mut pipeline_stats = PipelineStats::default();
+            let stats = or_exit(filter_lines(filename, &mut outputs, |line| {
+                let tokens = pipeline.tokens(line, &mut pipeline_stats);
+                let entropies = moore_lewis.score(&tokens);
+                let difference = entropies.difference();
+                (difference <= *threshold, format!("{:.6}\t{:.6}\t{:.6}", difference, entropies.in_domain, entropies.general))
+            }));
+            print_pipeline_stats(&pipeline_stats, &pipeline);
+            eprintln!("kept\t{}", stats.kept);
+            eprintln!("rejected\t{}", stats.rejected());
+            return;
+        }
+        Commands::filter_oov {filename, vocabulary, min_count, max_oov_ratio, min_mean_log_frequency, kept, rejected, scores, target, target_vocabulary, kept_target, rejected_target, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let load = |filename: &String| or_exit(read_vocabulary(&Some(filename.clone())).map_err(|e| format!("Can't read {}: {}", filename, e)));
+            let vocabulary = load(vocabulary);
+            let passes = |score: &OovScore| score.oov_ratio <= *max_oov_ratio && score.mean_log_frequency >= *min_mean_log_frequency;
+            let open = |filename: &Option<String>| filename.as_deref().map(|filename| or_exit(create(Some(filename))));
+            let mut outputs = FilterOutputs {
+                kept: or_exit(create(kept.as_deref())),
+                rejected: open(rejected),
+                scores: open(scores),
+            };
+
+            let mut pipeline_stats = PipelineStats::default();
+            let stats = match (filename, target, target_vocabulary) {
+                (Some(source), Some(target), Some(target_vocabulary)) => {
+                    let target_vocabulary = load(target_vocabulary);
+                    let mut target_outputs = FilterOutputs {
+                        kept: or_exit(create(kept_target.as_deref())),
+                        rejected: open(rejected_target),
+                        scores: None,
+                    };
+                    filter_pairs(source, target, &mut outputs, &mut target_outputs, |source_line, target_line| {
+                        let source_score = oov_score(&pipeline.tokens(source_line, &mut pipeline_stats), &vocabulary, *min_count);
+                        let target_score = oov_score(&pipeline.tokens(target_line, &mut pipeline_stats), &target_vocabulary, *min_count);
+                        (passes(&source_score) && passes(&target_score),
+                            format!("{:.6}\t{:.6}\t{:.6}\t{:.6}",
+                                source_score.oov_ratio,
+                                source_score.mean_log_frequency,
+                                target_score.oov_ratio,
+                                target_score.mean_log_frequency))
+                    })
+                }
+                _ => filter_lines(filename, &mut outputs, |line| {
+                    let score = oov_score(&pipeline.tokens(line, &mut pipeline_stats), &vocabulary, *min_count);
+                    (passes(&score), format!("{:.6}\t{:.6}", score.oov_ratio, score.mean_log_frequency))
+                }),
+            };
+            let stats = or_exit(stats);
+            print_pipeline_stats(&pipeline_stats, &pipeline);
+            eprintln!("kept\t{}", stats.kept);
+            eprintln!("rejected\t{}", stats.rejected());
+            return;
+        }
+        Commands::unkify {filename, vocabulary, min_count, classes, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let counts = match vocabulary {
+                Some(vocabulary) => or_exit(read_vocabulary(&Some(vocabulary.clone())).map_err(|e| format!("Can't read {}: {}", vocabulary, e))),
+                None if filename.as_deref().unwrap_or("-") == "-" => or_exit(Err("counting the vocabulary reads the input twice so it can't be stdin, use --vocabulary")),
+                None => or_exit(word_count_pipeline(filename, &pipeline)).0,
+            };
+            let unkifier = Unkifier::new(counts, *min_count, args.topk, *classes);
+
+            let stdout = io::stdout();
+            let (stats, pipeline_stats) = or_exit(unkify(filename, &pipeline, &unkifier, BufWriter::new(stdout.lock())));
+            print_pipeline_stats(&pipeline_stats, &pipeline);
+            eprintln!("vocabulary\t{}", unkifier.vocabulary.len());
+            eprintln!("unknown\t{}", stats.unknown);
+            eprintln!("unknown_rate\t{:.6}", stats.unknown as f64 / stats.tokens.max(1) as f64);
+            return;
+        }
+        Commands::parallel {source, target, prefix, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, _stats) = or_exit(word_count_parallel(source, target, &pipeline));
+            println!("lines\t{}", counts.lines);
+            for (side, side_counts, other) in [("source", &counts.source, &counts.target), ("target", &counts.target, &counts.source)] {
+                let coverage = coverage(side_counts, other);
+                println!("{}_tokens\t{}", side, coverage.tokens);
+                println!("{}_types\t{}", side, coverage.types);
+                println!("{}_shared_types\t{}", side, coverage.shared_types);
+                println!("{}_shared_token_ratio\t{:.6}", side, coverage.shared_tokens as f64 / coverage.tokens.max(1) as f64);
+            }
+            println!("joint_types\t{}", counts.joint.len());
+
+            let joint = sort_counts(&counts.joint);
+            if let Some(topk) = args.topk {
+                let vocabulary: HashSet<&String> = joint.iter().take(topk).map(|&(word, _)| word).collect();
+                println!("source_joint_top{}_coverage\t{:.6}", topk, token_coverage(&counts.source, &vocabulary));
+                println!("target_joint_top{}_coverage\t{:.6}", topk, token_coverage(&counts.target, &vocabulary));
+            }
+
+            if let Some(prefix) = prefix {
+                for (side, side_counts) in [("source", &counts.source), ("target", &counts.target), ("joint", &counts.joint)] {
+                    let filename = format!("{}.{}", prefix, side);
+                    let file = or_exit(File::create(&filename).map_err(|e| format!("Can't create {}: {}", filename, e)));
+                    let words = sort_counts(side_counts).into_iter().take(args.topk.unwrap_or(usize::MAX));
+                    or_exit(write_counts(BufWriter::new(file), words, &args.show_counts));
+                }
+            }
+            return;
+        }
+        Commands::cooc {source, target, min_word_count, min_count, per_source, max_pairs, sort_by, pipeline} => {
+            let pipeline = or_exit(pipeline.pipeline());
+            let (counts, stats) = or_exit(word_count_cooc(source, target, &pipeline, *min_word_count, *max_pairs));
+            print_pipeline_stats(&stats, &pipeline);
+            eprintln!("pairs\t{}", counts.pairs());
+            if counts.prunings > 0 {
+                eprintln!("prunings\t{}", counts.prunings);
+                eprintln!("prune_floor\t{}", counts.prune_floor);
+            }
+
+            let pairs = counts.scored(*min_count, *per_source, |pair| match sort_by.as_str() {
+                "pmi" => pair.pmi,
+                "count" => pair.count as f64,
+                _ => pair.dice,
+            });
+            for pair in pairs.into_iter().take(args.topk.unwrap_or(usize::MAX)) {
+                println!("{}\t{}\t{}\t{}\t{}\t{:.4}\t{:.4}",
+                    pair.source,
+                    pair.target,
+                    pair.count,
+                    pair.source_df,
+                    pair.target_df,
+                    pair.dice,
+                    pair.pmi);
+            }
+            return;
+        }
+        Commands::chars {filename, unit} => {
+            let unit = if unit == "grapheme" { CharUnit::Grapheme } else { CharUnit::CodePoint };
+            let counts = or_exit(char_count(filename, unit));
+            let summary = char_summary(&counts);
+            eprintln!("chars\t{}", counts.values().map(|&count| count as u64).sum::<u64>());
+            eprintln!("types\t{}", counts.len());
+            for (script, count) in &summary.scripts {
+                eprintln!("script_{}\t{}", script, count);
+            }
+            for (flag, count) in &summary.flags {
+                eprintln!("{}\t{}", flag.name(), count);
+            }
+
+            for (c, count) in sort_counts(&counts).into_iter().take(args.topk.unwrap_or(usize::MAX)) {
+                let info = CharInfo::new(c);
+                let flags: Vec<&str> = info.flags.iter().map(|flag| flag.name()).collect();
+                println!("{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    CharInfo::display(c),
+                    count,
+                    info.code_points,
+                    info.category,
+                    info.script,
+                    info.name,
+                    flags.join(","));
+            }
+            return;
+        }
+        Commands::external {filename, max_memory, tmp_dir} => {
+            let tmp_dir = tmp_dir.clone().unwrap_or_else(std::env::temp_dir);
+            let result = word_count_external(filename, *max_memory, &tmp_dir)
+                .and_then(|mut spills| {
+                    let merged = spills.merge()?;
+                    if args.freq_of_freq {
+                        let mut fof = FreqOfFreq::new();
+                        for entry in merged {
+                            let (_, count) = entry?;
+                            *fof.entry(count).or_insert(0usize) += 1usize;
+                        }
+                        eprintln!("spills\t{}", spills.len());
+                        print_freq_of_freq(&fof, args.log_bins);
+                        return Ok(());
+                    }
+                    match args.topk {
+                        Some(topk) => {
+                            let words = external_top(merged, topk)?;
+                            eprintln!("spills\t{}", spills.len());
+                            print_counts(words.into_iter(), &args.show_counts);
+                        }
+                        None => {
+                            // The vocabulary may not fit in memory either, sort it externally too.
+                            let sorted = sort_by_count(merged, *max_memory, &tmp_dir)?;
+                            eprintln!("spills\t{}", spills.len() + sorted.spills());
+                            let mut error = None;
+                            print_counts(sorted.map_while(|entry| entry.map_err(|e| error = Some(e)).ok()), &args.show_counts);
+                            if let Some(e) = error {
+                                return Err(e);
+                            }
+                        }
+                    }
+                    Ok(())
+                });
+            if let Err(e) = result {
+                eprintln!("Error counting externally: {}", e);
+                process::exit(1);
+            }
+            return;
+        }
     };
 
-    // [Word Frequency](http://rosettacode.org/wiki/Word_frequency#Rust)
-    let mut words: Vec<_> = counts.iter().collect();
-    words.sort_unstable_by_key(|&(word, count)| (Reverse(count), word));
+    if args.freq_of_freq {
+        print_freq_of_freq(&freq_of_freq(&counts), args.log_bins);
+        return;
+    }
+
+    let words = sort_counts(&counts);
 
     if let Some(topk) = args.topk {
         print_counts(words.into_iter().take(topk), &args.show_counts);
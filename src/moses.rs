@@ -0,0 +1,313 @@
+// vim:nowrap:
+
+// Port of the rules of Moses' tokenizer.perl
+// [tokenizer.perl](https://github.com/moses-smt/mosesdecoder/blob/master/scripts/tokenizer/tokenizer.perl)
+// The Penn treebank mode and the protected patterns are not supported.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+use regex::Regex;
+
+
+
+// Subsets of Moses' nonbreaking_prefix.* files.
+// The full files can be loaded with `MosesTokenizer::load_nonbreaking_prefixes`.
+const NONBREAKING_PREFIXES_EN: &str = "
+A B C D E F G H I J K L M N O P Q R S T U V W X Y Z
+Adj Adm Adv Asst Bart Bldg Brig Bros Capt Cmdr Col Comdr Con Corp Cpl DR Dr Drs Ens Gen Gov Hon Hr Hosp
+Insp Lt MM MR MRS MS Maj Messrs Mlle Mme Mr Mrs Ms Msgr Op Ord Pfc Ph Prof Pvt Rep Reps Res Rev Rt Sen
+Sens Sfc Sgt Sr St Supt Surg v vs i.e rev e.g
+Jan Feb Mar Apr Jun Jul Aug Sep Sept Oct Nov Dec
+No #NUMERIC_ONLY#
+Nos
+Art #NUMERIC_ONLY#
+Nr
+pp #NUMERIC_ONLY#
+";
+
+const NONBREAKING_PREFIXES_DE: &str = "
+A B C D E F G H I J K L M N O P Q R S T U V W X Y Z Ä Ö Ü
+a b c d e f g h i j k l m n o p q r s t u v w x y z ä ö ü
+I II III IV V VI VII VIII IX X XI XII XIII XIV XV XVI XVII XVIII XIX XX
+Abs Abt Adr Akk Aufl Az Bd Betr Bhf Bsp Bzw Chr Dipl Dr Evtl Fa Ffm Frl Gebr Hbf Hr Hrn Ing Inh Jh Jr
+Kap Kfm Kfz Mio Mrd Pkt Prof Pkw Reg Str Tel Tsd Usw Vgl Zt Zzgl
+abs abt bzw ca evtl ggf geb hbf inkl jh kap mio mrd o.ä s.o sog str tel tsd usw vgl z.B zt zzgl
+Jan Feb Mrz Apr Jun Jul Aug Sep Sept Okt Nov Dez
+Nr #NUMERIC_ONLY#
+Art #NUMERIC_ONLY#
+S #NUMERIC_ONLY#
+";
+
+const NONBREAKING_PREFIXES_FR: &str = "
+A B C D E F G H I J K L M N O P Q R S T U V W X Y Z
+a b c d e f g h i j k l m n o p q r s t u v w x y z
+Adj Adm Adv Art Ca C.-à-d Cap Cf Chap Co Dr Etc Ex Fig Hon Ibid Id M Me Mgr Min Mlle Mlles MM Mme Mmes
+Mr Mrs Ms Mt Op Ord Pr Prof Sr St Vol adj adv cf chap e.g etc ex fig ibid id i.e op p pp vol vs
+janv févr avr juil sept oct nov déc
+No #NUMERIC_ONLY#
+Nos
+n #NUMERIC_ONLY#
+";
+
+
+
+/// Whether a prefix followed by a period never ends a sentence or only does not before a number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Prefix {
+    Always,
+    NumericOnly,
+}
+
+
+
+/// Parse the content of a Moses `nonbreaking_prefix.*` file.
+/// Lines starting with `#` are comments and several prefixes per line are accepted.
+fn parse_nonbreaking_prefixes(content: &str) -> HashMap<String, Prefix> {
+    let mut prefixes = HashMap::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(prefix) = line.strip_suffix("#NUMERIC_ONLY#") {
+            prefixes.insert(prefix.trim().to_owned(), Prefix::NumericOnly);
+            continue;
+        }
+        for prefix in line.split_whitespace() {
+            prefixes.insert(prefix.to_owned(), Prefix::Always);
+        }
+    }
+
+    prefixes
+}
+
+
+
+/// Moses style tokenizer.
+#[derive(Debug, Clone)]
+pub struct MosesTokenizer {
+    lang: String,
+    /// Split hyphens between alphanumerics as `@-@`.
+    pub aggressive_hyphen: bool,
+    /// Escape `&|<>'"[]` as xml entities.
+    pub escape: bool,
+    prefixes: HashMap<String, Prefix>,
+    special: Regex,
+    colon: Option<Regex>,
+    hyphen: Regex,
+    multi_dots: Regex,
+    dot_multi: Regex,
+    comma_before: Regex,
+    comma_after: Regex,
+    apostrophes: Vec<(Regex, &'static str)>,
+    starts_lower: Regex,
+    starts_digit: Regex,
+    alpha: Regex,
+}
+
+
+
+impl MosesTokenizer {
+    /// Tokenizer for `lang` with its builtin nonbreaking prefixes, if any.
+    pub fn new(lang: &str) -> Self {
+        let builtin = match lang {
+            "en" => NONBREAKING_PREFIXES_EN,
+            "de" => NONBREAKING_PREFIXES_DE,
+            "fr" => NONBREAKING_PREFIXES_FR,
+            _ => "",
+        };
+
+        let (special, colon) = match lang {
+            // Finnish and Swedish use colons inside words.
+            "fi" | "sv" => (r"([^\p{Alphabetic}\p{Nd}\s\.:'`,\-])", Some(r":($|[^\p{Ll}])")),
+            _ => (r"([^\p{Alphabetic}\p{Nd}\s\.'`,\-])", None),
+        };
+
+        let apostrophes: Vec<(&str, &'static str)> = match lang {
+            "en" => vec![
+                (r"([^\p{Alphabetic}])'([^\p{Alphabetic}])", "$1 ' $2"),
+                (r"([^\p{Alphabetic}\p{N}])'(\p{Alphabetic})", "$1 ' $2"),
+                (r"(\p{Alphabetic})'([^\p{Alphabetic}])", "$1 ' $2"),
+                (r"(\p{Alphabetic})'(\p{Alphabetic})", "$1 '$2"),
+                (r"(\p{N})'(s)", "$1 '$2"),
+            ],
+            "fr" | "it" | "ga" | "ca" => vec![
+                (r"([^\p{Alphabetic}])'([^\p{Alphabetic}])", "$1 ' $2"),
+                (r"([^\p{Alphabetic}])'(\p{Alphabetic})", "$1 ' $2"),
+                (r"(\p{Alphabetic})'([^\p{Alphabetic}])", "$1 ' $2"),
+                (r"(\p{Alphabetic})'(\p{Alphabetic})", "$1' $2"),
+            ],
+            _ => vec![
+                (r"'", " ' "),
+            ],
+        };
+
+        MosesTokenizer {
+            lang: lang.to_owned(),
+            aggressive_hyphen: false,
+            escape: true,
+            prefixes: parse_nonbreaking_prefixes(builtin),
+            special: Regex::new(special).unwrap(),
+            colon: colon.map(|colon| Regex::new(colon).unwrap()),
+            hyphen: Regex::new(r"([\p{Alphabetic}\p{Nd}])-([\p{Alphabetic}\p{Nd}])").unwrap(),
+            multi_dots: Regex::new(r"\.(\.+)").unwrap(),
+            dot_multi: Regex::new(r"DOTMULTI\.([^\.])").unwrap(),
+            comma_before: Regex::new(r"([^\p{N}]),").unwrap(),
+            comma_after: Regex::new(r",([^\p{N}])").unwrap(),
+            apostrophes: apostrophes
+                .into_iter()
+                .map(|(pattern, replacement)| (Regex::new(pattern).unwrap(), replacement))
+                .collect(),
+            starts_lower: Regex::new(r"^\p{Ll}").unwrap(),
+            starts_digit: Regex::new(r"^[0-9]").unwrap(),
+            alpha: Regex::new(r"\p{Alphabetic}").unwrap(),
+        }
+    }
+
+    pub fn lang(&self) -> &str {
+        &self.lang
+    }
+
+    /// Replace the nonbreaking prefixes by those of a Moses `nonbreaking_prefix.*` file.
+    pub fn load_nonbreaking_prefixes(&mut self, filename: &str) -> io::Result<()> {
+        self.prefixes = parse_nonbreaking_prefixes(&fs::read_to_string(filename)?);
+
+        Ok(())
+    }
+
+    /// Tokenized `line` with tokens separated by single spaces.
+    pub fn tokenize(&self, line: &str) -> String {
+        // Normalize whitespace and remove ascii control characters.
+        let mut text: String = line
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .filter(|c| !c.is_ascii_control())
+            .collect();
+        text.insert(0, ' ');
+        text.push(' ');
+
+        // Separate out all "other" special characters.
+        text = self.special.replace_all(&text, " $1 ").into_owned();
+        if let Some(colon) = &self.colon {
+            text = colon.replace_all(&text, " : $1").into_owned();
+        }
+
+        if self.aggressive_hyphen {
+            // Replacements can't overlap, a-b-c needs two passes.
+            while self.hyphen.is_match(&text) {
+                text = self.hyphen.replace_all(&text, "$1 @-@ $2").into_owned();
+            }
+        }
+
+        // Multi-dots stay together.
+        text = self.multi_dots.replace_all(&text, " DOTMULTI$1").into_owned();
+        while text.contains("DOTMULTI.") {
+            text = self.dot_multi.replace_all(&text, "DOTDOTMULTI $1").into_owned();
+            text = text.replace("DOTMULTI.", "DOTDOTMULTI");
+        }
+
+        // Separate out "," except if within numbers (5,300).
+        text = self.comma_before.replace_all(&text, "$1 , ").into_owned();
+        text = self.comma_after.replace_all(&text, " , $1").into_owned();
+
+        for (apostrophe, replacement) in &self.apostrophes {
+            text = apostrophe.replace_all(&text, *replacement).into_owned();
+        }
+
+        // Split the final period of words that are not nonbreaking prefixes.
+        let words: Vec<&str> = text.split_whitespace().collect();
+        let mut tokens: Vec<String> = Vec::with_capacity(words.len());
+        for (i, word) in words.iter().enumerate() {
+            let pre = match word.strip_suffix('.') {
+                Some(pre) if !pre.is_empty() => pre,
+                _ => {
+                    tokens.push((*word).to_owned());
+                    continue;
+                }
+            };
+            let next = words.get(i + 1);
+            let keep = match next {
+                // Last words are unlikely to be nonbreaking prefixes.
+                None => false,
+                Some(next) => {
+                    (pre.contains('.') && self.alpha.is_match(pre))
+                    || self.prefixes.get(pre) == Some(&Prefix::Always)
+                    || self.starts_lower.is_match(next)
+                    || (self.prefixes.get(pre) == Some(&Prefix::NumericOnly) && self.starts_digit.is_match(next))
+                }
+            };
+            if keep {
+                tokens.push((*word).to_owned());
+            }
+            else {
+                tokens.push(pre.to_owned());
+                tokens.push(".".to_owned());
+            }
+        }
+        text = tokens.join(" ");
+
+        // .' at end of sentence is missed.
+        if let Some(stripped) = text.strip_suffix(".'") {
+            text = format!("{} . '", stripped);
+        }
+
+        // Restore multi-dots.
+        while text.contains("DOTDOTMULTI") {
+            text = text.replace("DOTDOTMULTI", "DOTMULTI.");
+        }
+        text = text.replace("DOTMULTI", ".");
+
+        if self.escape {
+            text = text
+                .replace('&', "&amp;")
+                .replace('|', "&#124;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('\'', "&apos;")
+                .replace('"', "&quot;")
+                .replace('[', "&#91;")
+                .replace(']', "&#93;");
+        }
+
+        text
+    }
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_examples() {
+        let mut moses = MosesTokenizer::new("en");
+        moses.escape = false;
+        assert_eq!(moses.tokenize("I don't know."), "I don 't know .");
+        assert_eq!(moses.tokenize("The U.S. economy grew."), "The U.S. economy grew .");
+        assert_eq!(moses.tokenize("It cost 5,300 dollars, or more."), "It cost 5,300 dollars , or more .");
+        assert_eq!(moses.tokenize("Mr. Smith left... Then (again) came back!"), "Mr. Smith left ... Then ( again ) came back !");
+        assert_eq!(moses.tokenize("a-b-c"), "a-b-c");
+
+        moses.aggressive_hyphen = true;
+        assert_eq!(moses.tokenize("a-b-c"), "a @-@ b @-@ c");
+    }
+
+    #[test]
+    fn escape() {
+        let moses = MosesTokenizer::new("en");
+        assert_eq!(moses.tokenize("Hello, \"world\" & <tags> [x]|"), "Hello , &quot; world &quot; &amp; &lt; tags &gt; &#91; x &#93; &#124;");
+        assert_eq!(moses.tokenize("don't"), "don &apos;t");
+    }
+
+    #[test]
+    fn french_apostrophes() {
+        let mut moses = MosesTokenizer::new("fr");
+        moses.escape = false;
+        assert_eq!(moses.tokenize("l'homme"), "l' homme");
+    }
+}
@@ -0,0 +1,112 @@
+// vim:nowrap:
+
+// Word n-grams are their tokens joined by a space, tokens never contain spaces.
+
+use std::borrow::Cow;
+use std::io::{
+    BufRead,
+    self,
+};
+
+use crate::pipeline::{
+    add_weight,
+    Pipeline,
+    PipelineStats,
+};
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+pub const BOS: &str = "<s>";
+pub const EOS: &str = "</s>";
+
+
+
+/// The n-grams of order `n` of `tokens`.
+pub fn ngrams<'t, S: AsRef<str>>(tokens: &'t [S], n: usize) -> impl Iterator<Item = String> + 't {
+    tokens
+        .windows(n.max(1))
+        .map(|window| {
+            window
+                .iter()
+                .map(AsRef::as_ref)
+                .collect::<Vec<&str>>()
+                .join(" ")
+        })
+}
+
+
+
+/// Counts of n-grams of orders 1 to `order`, `orders[i]` has the n-grams of order `i + 1`.
+#[derive(Debug, Clone, Default)]
+pub struct NgramCounts {
+    pub orders: Vec<Counts>,
+}
+
+
+
+impl NgramCounts {
+    pub fn new(order: usize) -> Self {
+        NgramCounts {
+            orders: vec![Counts::new(); order.max(1)],
+        }
+    }
+
+    pub fn order(&self) -> usize {
+        self.orders.len()
+    }
+
+    /// Count all the n-grams of `tokens` up to the highest order.
+    pub fn add<S: AsRef<str>>(&mut self, tokens: &[S], weight: u32) -> io::Result<()> {
+        for (i, counts) in self.orders.iter_mut().enumerate() {
+            for ngram in ngrams(tokens, i + 1) {
+                match counts.get_mut(&ngram) {
+                    Some(count) => add_weight(count, weight, &ngram)?,
+                    None => {
+                        counts.insert(ngram, weight);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Count of an n-gram whose order is its number of tokens.
+    pub fn get(&self, ngram: &str) -> u32 {
+        let order = ngram.split(' ').count();
+        self.orders
+            .get(order - 1)
+            .and_then(|counts| counts.get(ngram))
+            .copied()
+            .unwrap_or(0)
+    }
+}
+
+
+
+/// Buffer - While - for-loop through the shared tokenizer pipeline counting n-grams of orders 1
+/// to `order`.
+/// With `boundaries`, each line is wrapped in `<s>` and `</s>` like for language models.
+pub fn word_count_ngrams(filename: &Option<String>, pipeline: &Pipeline, order: usize, boundaries: bool) -> io::Result<(NgramCounts, PipelineStats)> {
+    let mut counts = NgramCounts::new(order);
+    let mut stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        if let Some(weight) = pipeline.weight(&line, &mut stats) {
+            let mut tokens = pipeline.tokens(&line, &mut stats);
+            if boundaries {
+                tokens.insert(0, Cow::Borrowed(BOS));
+                tokens.push(Cow::Borrowed(EOS));
+            }
+            counts.add(&tokens, weight)?;
+        }
+        line.clear();
+    }
+
+    Ok((counts, stats))
+}
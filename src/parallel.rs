@@ -0,0 +1,137 @@
+// vim:nowrap:
+
+// Vocabularies of sentence aligned source and target files.
+
+use std::collections::HashSet;
+use std::io::{
+    BufRead,
+    self,
+};
+
+use crate::pipeline::{
+    Pipeline,
+    PipelineStats,
+};
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+/// Source, target and joint vocabularies of a parallel corpus.
+#[derive(Debug, Clone, Default)]
+pub struct ParallelCounts {
+    pub source: Counts,
+    pub target: Counts,
+    pub joint: Counts,
+    /// Number of sentence pairs.
+    pub lines: u64,
+}
+
+
+
+/// How much of one side's vocabulary is also seen on the other side.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Coverage {
+    pub tokens: u64,
+    pub types: usize,
+    /// Types also seen on the other side.
+    pub shared_types: usize,
+    /// Tokens whose type is also seen on the other side.
+    pub shared_tokens: u64,
+}
+
+
+
+/// Coverage of `counts` by the types of `other`.
+pub fn coverage(counts: &Counts, other: &Counts) -> Coverage {
+    counts
+        .iter()
+        .fold(Coverage::default(), |mut coverage, (word, &count)| {
+            coverage.tokens += count as u64;
+            coverage.types += 1;
+            if other.contains_key(word) {
+                coverage.shared_types += 1;
+                coverage.shared_tokens += count as u64;
+            }
+            coverage
+        })
+}
+
+
+
+/// Fraction of the tokens of `counts` whose type is in `vocabulary`.
+pub fn token_coverage(counts: &Counts, vocabulary: &HashSet<&String>) -> f64 {
+    let (covered, total) = counts
+        .iter()
+        .fold((0u64, 0u64), |(covered, total), (word, &count)| {
+            if vocabulary.contains(word) {
+                (covered + count as u64, total + count as u64)
+            }
+            else {
+                (covered, total + count as u64)
+            }
+        });
+
+    if total == 0 { 0.0 } else { covered as f64 / total as f64 }
+}
+
+
+
+/// Buffer - While - loop through the aligned lines of `source` and `target`, returns the number of
+/// sentence pairs.
+/// Fails if the files don't have the same number of lines or `f` fails.
+pub fn for_each_pair<F>(source: &str, target: &str, mut f: F) -> io::Result<u64>
+    where F: FnMut(&str, &str) -> io::Result<()>
+{
+    let open = |filename: &str| try_get_reader(&Some(filename.to_owned()))
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", filename, e)));
+    let mut source_rdr = open(source)?;
+    let mut target_rdr = open(target)?;
+    let mut source_line = String::with_capacity(1024);
+    let mut target_line = String::with_capacity(1024);
+    let mut lines = 0u64;
+    loop {
+        let source_read = source_rdr.read_line(&mut source_line)?;
+        let target_read = target_rdr.read_line(&mut target_line)?;
+        if source_read == 0 && target_read == 0 {
+            break;
+        }
+        if source_read == 0 || target_read == 0 {
+            let (shorter, longer) = if source_read == 0 { (source, target) } else { (target, source) };
+            return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{} has {} lines but {} has more", shorter, lines, longer)));
+        }
+        lines += 1;
+
+        f(&source_line, &target_line)?;
+
+        source_line.clear();
+        target_line.clear();
+    }
+
+    Ok(lines)
+}
+
+
+
+/// Count both sides of a parallel corpus in one pass.
+/// Fails if the files don't have the same number of lines.
+pub fn word_count_parallel(source: &str, target: &str, pipeline: &Pipeline) -> io::Result<(ParallelCounts, PipelineStats)> {
+    let mut counts = ParallelCounts::default();
+    let mut stats = PipelineStats::default();
+
+    counts.lines = for_each_pair(source, target, |source_line, target_line| {
+        for (line, side) in [(source_line, &mut counts.source), (target_line, &mut counts.target)] {
+            for token in pipeline.tokens(line, &mut stats) {
+                *counts.joint.entry(token.to_string()).or_insert(0u32) += 1u32;
+                *side.entry(token.into_owned()).or_insert(0u32) += 1u32;
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok((counts, stats))
+}
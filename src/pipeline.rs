@@ -0,0 +1,704 @@
+// vim:nowrap:
+
+// The shared tokenizer pipeline used by the configurable commands.
+// Each line goes through the following stages:
+//   0. weight and field selection or text extraction from JSONL
+//   1. unicode normalization of each selected field
+//   2. whitespace, regex or moses tokenization, or the whole line as a single unit
+//   3. lowercasing or case folding
+//   4. character or grapheme cluster n-grams of each token
+//   5. hashing of line units
+
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{
+    BTreeMap,
+    HashMap,
+};
+use std::hash::{
+    Hash,
+    Hasher,
+};
+use std::io::{
+    BufRead,
+    self,
+};
+use std::str::FromStr;
+
+use regex::Regex;
+use unicode_normalization::{
+    is_nfc_quick,
+    is_nfd_quick,
+    is_nfkc_quick,
+    is_nfkd_quick,
+    IsNormalized,
+    UnicodeNormalization,
+};
+
+use crate::chars::CharUnit;
+use crate::jsonl::{
+    parse_line,
+    JsonPath,
+};
+use crate::moses::MosesTokenizer;
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+/// [Unicode normalization forms](https://unicode.org/reports/tr15/)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(form: &str) -> Result<Self, Self::Err> {
+        match form.to_lowercase().as_str() {
+            "nfc" => Ok(Normalization::Nfc),
+            "nfd" => Ok(Normalization::Nfd),
+            "nfkc" => Ok(Normalization::Nfkc),
+            "nfkd" => Ok(Normalization::Nfkd),
+            _ => Err(format!("unknown normalization form: {}", form)),
+        }
+    }
+}
+
+
+
+impl Normalization {
+    /// Normalized `text` or None if it is already normalized.
+    pub fn apply(&self, text: &str) -> Option<String> {
+        let quick = match self {
+            Normalization::Nfc => is_nfc_quick(text.chars()),
+            Normalization::Nfd => is_nfd_quick(text.chars()),
+            Normalization::Nfkc => is_nfkc_quick(text.chars()),
+            Normalization::Nfkd => is_nfkd_quick(text.chars()),
+        };
+        if quick == IsNormalized::Yes {
+            return None;
+        }
+
+        let normalized: String = match self {
+            Normalization::Nfc => text.nfc().collect(),
+            Normalization::Nfd => text.nfd().collect(),
+            Normalization::Nfkc => text.nfkc().collect(),
+            Normalization::Nfkd => text.nfkd().collect(),
+        };
+        if normalized == text {
+            None
+        }
+        else {
+            Some(normalized)
+        }
+    }
+}
+
+
+
+/// How to ignore case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// `str::to_lowercase`.
+    Lower,
+    /// [Full unicode case folding](https://www.unicode.org/Public/UCD/latest/ucd/CaseFolding.txt), e.g. `ß` folds to `ss`.
+    Fold,
+}
+
+
+
+impl Case {
+    /// Caseless `token` or None if it has no case to remove.
+    pub fn apply(&self, token: &str) -> Option<String> {
+        let caseless = match self {
+            Case::Lower => token.to_lowercase(),
+            Case::Fold => caseless::default_case_fold_str(token),
+        };
+        if caseless == token {
+            None
+        }
+        else {
+            Some(caseless)
+        }
+    }
+}
+
+
+
+/// How lines are split into tokens.
+#[derive(Debug, Clone, Default)]
+pub enum Tokenizer {
+    /// Split on unicode whitespace like the word_count_* strategies.
+    #[default]
+    Whitespace,
+    /// Tokens are the matches of the regex.
+    Pattern(Regex),
+    /// Split on the matches of the regex.
+    Split(Regex),
+    Moses(Box<MosesTokenizer>),
+}
+
+
+
+/// Which fields of delimited lines, e.g. TSV, to tokenize.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Fields {
+    /// The whole line.
+    #[default]
+    Line,
+    /// Only these fields, numbered from 1.
+    Select(Vec<usize>),
+    /// Every field.
+    All,
+}
+
+
+
+/// Where to find the text, and optionally its group, in JSONL documents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonInput {
+    pub text: JsonPath,
+    pub group_by: Option<JsonPath>,
+}
+
+
+
+/// What is counted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Unit {
+    #[default]
+    Token,
+    /// The whole line, or selected fields, like `sort | uniq -c`.
+    Line,
+}
+
+
+
+/// Character n-grams stage, replaces each token by its overlapping sequences of `order`
+/// characters, `order` 1 giving a character vocabulary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CharNgrams {
+    pub order: usize,
+    pub unit: CharUnit,
+}
+
+
+
+/// Options of the line unit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LineUnit {
+    /// Trim and squeeze whitespace.
+    pub trim: bool,
+    /// Replace lines by a 64 bit hash to save memory.
+    pub hash: bool,
+}
+
+
+
+/// What the pipeline did so far.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineStats {
+    pub lines: u64,
+    pub tokens: u64,
+    /// Tokens changed by the unicode normalization, i.e. missing from the tokens of the raw text.
+    pub normalized: u64,
+    /// Selected fields absent from their line.
+    pub missing_fields: u64,
+    /// Lines that are not valid json.
+    pub invalid_json: u64,
+    /// Lines skipped because their weight field is missing or not a count.
+    pub invalid_weights: u64,
+}
+
+
+
+/// Configuration of the shared tokenizer pipeline.
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    /// Parse lines as json, replaces the field selection.
+    pub json: Option<JsonInput>,
+    pub fields: Fields,
+    /// Field delimiter, only used when selecting fields or weighting.
+    /// A space delimiter ignores leading whitespace like in `uniq -c` output.
+    pub delimiter: char,
+    /// Field, numbered from 1, holding how many times each line should be counted.
+    /// It is excluded from the text.
+    pub weight_field: Option<usize>,
+    pub tokenizer: Tokenizer,
+    pub normalization: Option<Normalization>,
+    pub case: Option<Case>,
+    pub char_ngrams: Option<CharNgrams>,
+    pub unit: Unit,
+    pub line_unit: LineUnit,
+}
+
+
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Pipeline {
+            json: None,
+            fields: Fields::default(),
+            delimiter: '\t',
+            weight_field: None,
+            tokenizer: Tokenizer::default(),
+            normalization: None,
+            case: None,
+            char_ngrams: None,
+            unit: Unit::default(),
+            line_unit: LineUnit::default(),
+        }
+    }
+}
+
+
+
+impl Pipeline {
+    /// Tokens of `line` after all the stages.
+    pub fn tokens<'a>(&self, line: &'a str, stats: &mut PipelineStats) -> Vec<Cow<'a, str>> {
+        let mut tokens = self.surface_tokens(line, stats);
+        self.last_stages(&mut tokens);
+
+        tokens
+    }
+
+    /// Tokens of `line` with their original casing, i.e. before the case stage.
+    /// A line unit is made of all the selected fields joined by the delimiter.
+    pub fn surface_tokens<'a>(&self, line: &'a str, stats: &mut PipelineStats) -> Vec<Cow<'a, str>> {
+        let fields = self.fields(line, stats);
+        if self.unit == Unit::Line && fields.len() > 1 {
+            let mut delimiter = [0u8; 4];
+            let text = fields
+                .iter()
+                .map(|(_, text)| text.as_ref())
+                .collect::<Vec<&str>>()
+                .join(self.delimiter.encode_utf8(&mut delimiter));
+            return self.tokenize_cow(Cow::Owned(text), stats);
+        }
+
+        fields
+            .into_iter()
+            .flat_map(|(_, text)| self.tokenize_cow(text, stats))
+            .collect()
+    }
+
+    /// Group and tokens of a JSONL `line` after all the stages.
+    /// Documents without a group are in the empty group.
+    pub fn grouped_tokens(&self, line: &str, stats: &mut PipelineStats) -> Option<(String, Vec<Cow<'static, str>>)> {
+        stats.lines += 1;
+
+        let (group, text) = self.json_fields(line, stats)?;
+        let mut tokens = self.tokenize_cow(Cow::Owned(text), stats);
+        self.last_stages(&mut tokens);
+
+        Some((group.unwrap_or_default(), tokens))
+    }
+
+    /// Tokens of each selected field of `line` after all the stages.
+    pub fn field_tokens<'a>(&self, line: &'a str, stats: &mut PipelineStats) -> Vec<(usize, Vec<Cow<'a, str>>)> {
+        self.fields(line, stats)
+            .into_iter()
+            .map(|(field, text)| {
+                let mut tokens = self.tokenize_cow(text, stats);
+                self.last_stages(&mut tokens);
+                (field, tokens)
+            })
+            .collect()
+    }
+
+    /// Group and text of a JSONL `line`.
+    fn json_fields(&self, line: &str, stats: &mut PipelineStats) -> Option<(Option<String>, String)> {
+        let json = self.json.as_ref()?;
+        let document = match parse_line(line) {
+            Some(document) => document,
+            None => {
+                stats.invalid_json += 1;
+                return None;
+            }
+        };
+        let text = match json.text.text(&document) {
+            Some(text) => text,
+            None => {
+                stats.missing_fields += 1;
+                return None;
+            }
+        };
+        let group = json.group_by.as_ref().and_then(|group_by| group_by.text(&document));
+
+        Some((group, text))
+    }
+
+    /// Selected fields of `line` as `(field, text)`.
+    /// The whole line or json text is field 0.
+    fn fields<'a>(&self, line: &'a str, stats: &mut PipelineStats) -> Vec<(usize, Cow<'a, str>)> {
+        stats.lines += 1;
+
+        if self.json.is_some() {
+            return self.json_fields(line, stats)
+                .map(|(_, text)| (0, Cow::Owned(text)))
+                .into_iter()
+                .collect();
+        }
+
+        if self.fields == Fields::Line && self.weight_field.is_none() {
+            return vec![(0, Cow::Borrowed(line.trim_end_matches(['\n', '\r'])))];
+        }
+
+        let columns = self.columns(line);
+        let is_text = |field: &usize| Some(*field) != self.weight_field;
+        match &self.fields {
+            Fields::Line => (1..=columns.len())
+                .filter(is_text)
+                .map(|field| (0, Cow::Borrowed(columns[field - 1])))
+                .collect(),
+            Fields::All => (1..=columns.len())
+                .filter(is_text)
+                .map(|field| (field, Cow::Borrowed(columns[field - 1])))
+                .collect(),
+            Fields::Select(fields) => {
+                fields
+                    .iter()
+                    .filter_map(|&field| {
+                        let text = field.checked_sub(1).and_then(|i| columns.get(i));
+                        if text.is_none() {
+                            stats.missing_fields += 1;
+                        }
+                        text.map(|text| (field, Cow::Borrowed(*text)))
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Delimited fields of `line`.
+    fn columns<'a>(&self, line: &'a str) -> Vec<&'a str> {
+        let mut line = line.trim_end_matches(['\n', '\r']);
+        if self.delimiter == ' ' {
+            line = line.trim_start();
+        }
+
+        line.split(self.delimiter).collect()
+    }
+
+    /// How many times `line` should be counted, 1 without a weight field.
+    /// None if the weight is missing or invalid, the line should then be skipped.
+    pub fn weight(&self, line: &str, stats: &mut PipelineStats) -> Option<u32> {
+        let field = match self.weight_field {
+            Some(field) => field,
+            None => return Some(1),
+        };
+        let weight = field
+            .checked_sub(1)
+            .and_then(|i| self.columns(line).get(i).and_then(|weight| weight.trim().parse::<u32>().ok()));
+        if weight.is_none() {
+            stats.invalid_weights += 1;
+        }
+
+        weight
+    }
+
+    /// Normalize then tokenize borrowed text or owned text, e.g. extracted from json.
+    fn tokenize_cow<'a>(&self, text: Cow<'a, str>, stats: &mut PipelineStats) -> Vec<Cow<'a, str>> {
+        let normalized = match self.normalization.and_then(|normalization| normalization.apply(&text)) {
+            Some(normalized) => normalized,
+            None => {
+                return match text {
+                    Cow::Borrowed(text) => self.tokenize(text, stats),
+                    Cow::Owned(text) => self.tokenize_owned(&text, stats),
+                };
+            }
+        };
+
+        let tokens = self.tokenize_owned(&normalized, stats);
+        // The raw tokens are only compared, they don't count as tokens.
+        let raw_tokens = self.tokenize(&text, &mut PipelineStats::default());
+        let mut raw: HashMap<&str, usize> = HashMap::new();
+        for token in &raw_tokens {
+            *raw.entry(token.as_ref()).or_insert(0usize) += 1usize;
+        }
+        for token in &tokens {
+            match raw.get_mut(token.as_ref()) {
+                Some(count) if *count > 0 => *count -= 1,
+                _ => stats.normalized += 1,
+            }
+        }
+
+        tokens
+    }
+
+    /// Tokenize text that doesn't outlive the call.
+    fn tokenize_owned<'a>(&self, text: &str, stats: &mut PipelineStats) -> Vec<Cow<'a, str>> {
+        self.tokenize(text, stats)
+            .into_iter()
+            .map(|token| Cow::Owned(token.into_owned()))
+            .collect()
+    }
+
+    /// Tokenization stage.
+    fn tokenize<'a>(&self, line: &'a str, stats: &mut PipelineStats) -> Vec<Cow<'a, str>> {
+        let tokens: Vec<Cow<'a, str>> = match &self.tokenizer {
+            _ if self.unit == Unit::Line => {
+                if self.line_unit.trim {
+                    vec![Cow::Owned(line.split_whitespace().collect::<Vec<_>>().join(" "))]
+                }
+                else {
+                    vec![Cow::Borrowed(line)]
+                }
+            }
+            Tokenizer::Whitespace => line
+                .split(char::is_whitespace)
+                .filter(|token| !token.is_empty())
+                .map(Cow::Borrowed)
+                .collect(),
+            Tokenizer::Pattern(regex) => regex
+                .find_iter(line)
+                .map(|m| m.as_str())
+                .filter(|token| !token.is_empty())
+                .map(Cow::Borrowed)
+                .collect(),
+            Tokenizer::Split(regex) => regex
+                .split(line)
+                .filter(|token| !token.is_empty())
+                .map(Cow::Borrowed)
+                .collect(),
+            Tokenizer::Moses(moses) => moses
+                .tokenize(line)
+                .split(' ')
+                .filter(|token| !token.is_empty())
+                .map(|token| Cow::Owned(token.to_owned()))
+                .collect(),
+        };
+
+        stats.tokens += tokens.len() as u64;
+
+        tokens
+    }
+
+    /// Case, character n-grams and hashing stages.
+    fn last_stages<'a>(&self, tokens: &mut Vec<Cow<'a, str>>) {
+        if self.case.is_some() {
+            for token in tokens.iter_mut() {
+                if let Some(caseless) = self.uncase(token) {
+                    *token = Cow::Owned(caseless);
+                }
+            }
+        }
+        if let Some(CharNgrams { order, unit }) = self.char_ngrams {
+            *tokens = tokens
+                .drain(..)
+                .flat_map(|token| match token {
+                    Cow::Borrowed(token) => unit.windows(token, order).map(Cow::Borrowed).collect::<Vec<_>>(),
+                    Cow::Owned(token) => unit.windows(&token, order).map(|ngram| Cow::Owned(ngram.to_owned())).collect(),
+                })
+                .collect();
+        }
+        if self.unit == Unit::Line && self.line_unit.hash {
+            for token in tokens.iter_mut() {
+                let mut hasher = DefaultHasher::new();
+                token.hash(&mut hasher);
+                *token = Cow::Owned(format!("{:016x}", hasher.finish()));
+            }
+        }
+    }
+
+    /// Apply the case stage to a surface token, None if it is unchanged.
+    pub fn uncase(&self, token: &str) -> Option<String> {
+        self.case.and_then(|case| case.apply(token))
+    }
+}
+
+
+
+/// Add the `weight` of a line to the `count` of `token`, failing rather than wrapping around.
+pub fn add_weight(count: &mut u32, weight: u32, token: &str) -> io::Result<()> {
+    *count = count.checked_add(weight).ok_or_else(|| io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("the count of {:?} overflows", token)))?;
+    Ok(())
+}
+
+
+
+/// Buffer - While - for-loop through the shared tokenizer pipeline.
+pub fn word_count_pipeline(filename: &Option<String>, pipeline: &Pipeline) -> io::Result<(Counts, PipelineStats)> {
+    let mut counts = Counts::new();
+    let mut stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        if let Some(weight) = pipeline.weight(&line, &mut stats) {
+            for token in pipeline.tokens(&line, &mut stats) {
+                match counts.get_mut(token.as_ref()) {
+                    Some(count) => add_weight(count, weight, &token)?,
+                    None => {
+                        counts.insert(token.into_owned(), weight);
+                    }
+                }
+            }
+        }
+        line.clear();
+    }
+
+    Ok((counts, stats))
+}
+
+
+
+/// How many lines are repeated, when counting line units.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DuplicateStats {
+    pub lines: u64,
+    pub unique_lines: usize,
+    /// Distinct lines seen more than once.
+    pub repeated_lines: usize,
+    /// Lines that are a copy of a previous line, `lines - unique_lines`.
+    pub duplicates: u64,
+}
+
+
+
+impl DuplicateStats {
+    pub fn duplicate_rate(&self) -> f64 {
+        if self.lines == 0 { 0.0 } else { self.duplicates as f64 / self.lines as f64 }
+    }
+}
+
+
+
+/// Duplicate statistics of line counts.
+pub fn duplicate_stats(counts: &Counts) -> DuplicateStats {
+    let lines: u64 = counts.values().map(|&count| count as u64).sum();
+    DuplicateStats {
+        lines,
+        unique_lines: counts.len(),
+        repeated_lines: counts.values().filter(|&&count| count > 1).count(),
+        duplicates: lines - counts.len() as u64,
+    }
+}
+
+
+
+/// Buffer - While - for-loop through the shared tokenizer pipeline with one vocabulary per field.
+/// Every field is counted if the pipeline selects the whole line.
+pub fn word_count_per_field(filename: &Option<String>, pipeline: &Pipeline) -> io::Result<(BTreeMap<usize, Counts>, PipelineStats)> {
+    let mut pipeline = pipeline.clone();
+    if pipeline.fields == Fields::Line {
+        pipeline.fields = Fields::All;
+    }
+
+    let mut counts: BTreeMap<usize, Counts> = BTreeMap::new();
+    let mut stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        if let Some(weight) = pipeline.weight(&line, &mut stats) {
+            for (field, tokens) in pipeline.field_tokens(&line, &mut stats) {
+                let counts = counts.entry(field).or_default();
+                for token in tokens {
+                    let count = counts.entry(token.to_string()).or_insert(0u32);
+                    add_weight(count, weight, &token)?;
+                }
+            }
+        }
+        line.clear();
+    }
+
+    Ok((counts, stats))
+}
+
+
+
+/// Buffer - While - for-loop over JSONL with one vocabulary per group.
+pub fn word_count_grouped(filename: &Option<String>, pipeline: &Pipeline) -> io::Result<(BTreeMap<String, Counts>, PipelineStats)> {
+    let mut counts: BTreeMap<String, Counts> = BTreeMap::new();
+    let mut stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        if let Some((group, tokens)) = pipeline.grouped_tokens(&line, &mut stats) {
+            let counts = counts.entry(group).or_default();
+            for token in tokens {
+                *counts.entry(token.into_owned()).or_insert(0u32) += 1u32;
+            }
+        }
+        line.clear();
+    }
+
+    Ok((counts, stats))
+}
+
+
+
+/// Vocabulary entry keeping track of the surface casings of a caseless type.
+#[derive(Debug, Clone, Default)]
+pub struct CasedEntry {
+    pub count: u32,
+    pub casings: HashMap<String, u32>,
+}
+
+
+
+impl CasedEntry {
+    /// Casings sorted by decreasing count then alphabetically.
+    pub fn sorted_casings(&self) -> Vec<(&String, &u32)> {
+        let mut casings: Vec<_> = self.casings.iter().collect();
+        casings.sort_unstable_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        casings
+    }
+
+    /// Most frequent casing, what a truecaser would restore.
+    pub fn best_casing(&self) -> Option<&String> {
+        self.sorted_casings().first().map(|&(casing, _)| casing)
+    }
+}
+
+
+
+/// Caseless type to its count and surface casings.
+pub type CasedCounts = HashMap<String, CasedEntry>;
+
+
+
+/// Buffer - While - for-loop through the shared tokenizer pipeline recording the surface casings.
+/// Uses case folding if the pipeline has no case stage.
+pub fn word_count_casings(filename: &Option<String>, pipeline: &Pipeline) -> io::Result<(CasedCounts, PipelineStats)> {
+    let mut pipeline = pipeline.clone();
+    pipeline.case.get_or_insert(Case::Fold);
+
+    let mut counts = CasedCounts::new();
+    let mut stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        let weight = match pipeline.weight(&line, &mut stats) {
+            Some(weight) => weight,
+            None => {
+                line.clear();
+                continue;
+            }
+        };
+        for surface in pipeline.surface_tokens(&line, &mut stats) {
+            let caseless = pipeline.uncase(&surface).unwrap_or_else(|| surface.to_string());
+            let entry = counts.entry(caseless).or_default();
+            add_weight(&mut entry.count, weight, &surface)?;
+            add_weight(entry.casings.entry(surface.to_string()).or_insert(0u32), weight, &surface)?;
+        }
+        line.clear();
+    }
+
+    Ok((counts, stats))
+}
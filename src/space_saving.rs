@@ -0,0 +1,169 @@
+// vim:nowrap:
+
+// [Efficient Computation of Frequent and Top-k Elements in Data Streams](https://www.cs.ucsb.edu/sites/default/files/documents/2005-23.pdf)
+// Metwally, Agrawal and El Abbadi's Space-Saving algorithm.
+
+use std::collections::{
+    BTreeSet,
+    HashMap,
+};
+use std::io::{
+    BufRead,
+    self,
+};
+
+use crate::try_get_reader;
+
+
+
+/// Approximate count of a monitored token.
+/// The true count lies in `[count - error, count]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub count: u64,
+    pub error: u64,
+}
+
+
+
+/// Space-Saving heavy hitters with at most `capacity` monitored tokens.
+/// Monitored tokens live in numbered slots, reused on eviction, so that hits only update counts.
+pub struct SpaceSaving {
+    capacity: usize,
+    tokens: u64,
+    ids: HashMap<String, usize>,
+    words: Vec<String>,
+    estimates: Vec<Estimate>,
+    // Slots ordered by count to find the one to evict.
+    by_count: BTreeSet<(u64, usize)>,
+}
+
+
+
+impl SpaceSaving {
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        SpaceSaving {
+            capacity,
+            tokens: 0,
+            ids: HashMap::with_capacity(capacity),
+            words: Vec::with_capacity(capacity),
+            estimates: Vec::with_capacity(capacity),
+            by_count: BTreeSet::new(),
+        }
+    }
+
+    /// Count one occurrence of `word`.
+    pub fn insert(&mut self, word: &str) {
+        self.tokens += 1;
+
+        if let Some(&id) = self.ids.get(word) {
+            let estimate = &mut self.estimates[id];
+            self.by_count.remove(&(estimate.count, id));
+            estimate.count += 1;
+            self.by_count.insert((estimate.count, id));
+            return;
+        }
+
+        let id = if self.words.len() < self.capacity {
+            self.words.push(word.to_owned());
+            self.estimates.push(Estimate { count: 1, error: 0 });
+            self.words.len() - 1
+        }
+        else {
+            // Replace the token with the smallest count and inherit its count as error.
+            let (min_count, id) = self.by_count.pop_first().expect("capacity is at least 1");
+            let evicted = std::mem::replace(&mut self.words[id], word.to_owned());
+            self.ids.remove(&evicted);
+            self.estimates[id] = Estimate { count: min_count + 1, error: min_count };
+            id
+        };
+        self.by_count.insert((self.estimates[id].count, id));
+        self.ids.insert(word.to_owned(), id);
+    }
+
+    /// Total number of tokens seen.
+    pub fn tokens(&self) -> u64 {
+        self.tokens
+    }
+
+    /// Upper bound on the count of any token that is not monitored.
+    pub fn min_count(&self) -> u64 {
+        if self.words.len() < self.capacity {
+            0
+        }
+        else {
+            self.by_count.first().map(|(count, _)| *count).unwrap_or(0)
+        }
+    }
+
+    /// Monitored tokens sorted by decreasing estimated count then alphabetically.
+    pub fn top(&self) -> Vec<(&String, &Estimate)> {
+        let mut top: Vec<_> = self.words.iter().zip(&self.estimates).collect();
+        top.sort_unstable_by(|a, b| b.1.count.cmp(&a.1.count).then(a.0.cmp(b.0)));
+
+        top
+    }
+}
+
+
+
+/// Buffer - While - for-loop into a Space-Saving summary.
+pub fn word_count_space_saving(filename: &Option<String>, capacity: usize) -> io::Result<SpaceSaving> {
+    let mut summary = SpaceSaving::new(capacity);
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        for word in line.split(char::is_whitespace) {
+            if !word.is_empty() {
+                summary.insert(word);
+            }
+        }
+        line.clear();
+    }
+
+    Ok(summary)
+}
+
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn error_bound() {
+        // Token i is seen 600 / (i + 1) times, interleaved.
+        let truth: HashMap<String, u64> = (0..200).map(|i| (format!("w{}", i), 600 / (i + 1))).collect();
+        let mut summary = SpaceSaving::new(20);
+        for round in 0..600 {
+            for i in 0..200 {
+                if round < 600 / (i + 1) {
+                    summary.insert(&format!("w{}", i));
+                }
+            }
+        }
+        assert_eq!(summary.tokens(), truth.values().sum::<u64>());
+
+        let top = summary.top();
+        assert_eq!(top.len(), 20);
+        for (word, estimate) in &top {
+            let count = truth[*word];
+            assert!(estimate.count - estimate.error <= count && count <= estimate.count, "{} {:?} {}", word, estimate, count);
+        }
+        // Tokens more frequent than N / capacity are always monitored.
+        let monitored: HashSet<&String> = top.iter().map(|(word, _)| *word).collect();
+        for (word, &count) in &truth {
+            if count > summary.tokens() / 20 {
+                assert!(monitored.contains(word), "{} is missing", word);
+            }
+            else if !monitored.contains(word) {
+                assert!(count <= summary.min_count());
+            }
+        }
+        assert_eq!(top[0].0, "w0");
+    }
+}
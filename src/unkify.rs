@@ -0,0 +1,125 @@
+// vim:nowrap:
+
+// Out-of-vocabulary tokens are replaced by `<unk>` or, with classes, by an unknown token telling
+// whether they look like a number, punctuation or a capitalized word, in that order.
+
+use std::collections::HashSet;
+use std::io::{
+    BufRead,
+    Write,
+    self,
+};
+
+use crate::arpa::UNK;
+use crate::pipeline::{
+    Pipeline,
+    PipelineStats,
+};
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+pub const UNK_NUM: &str = "<unk-num>";
+pub const UNK_PUNCT: &str = "<unk-punct>";
+pub const UNK_CAP: &str = "<unk-cap>";
+
+
+
+/// Class based unknown token of an out-of-vocabulary token.
+pub fn unk_class(token: &str) -> &'static str {
+    if token.chars().any(char::is_numeric) {
+        UNK_NUM
+    }
+    else if !token.is_empty() && token.chars().all(|c| !c.is_alphanumeric()) {
+        UNK_PUNCT
+    }
+    else if token.chars().next().is_some_and(char::is_uppercase) {
+        UNK_CAP
+    }
+    else {
+        UNK
+    }
+}
+
+
+
+/// Replaces the tokens missing from a vocabulary.
+#[derive(Debug, Clone, Default)]
+pub struct Unkifier {
+    pub vocabulary: HashSet<String>,
+    /// Use class based unknown tokens.
+    pub classes: bool,
+}
+
+
+
+impl Unkifier {
+    /// Vocabulary of the tokens seen at least `min_count` times, or of the `topk` most frequent.
+    pub fn new(counts: Counts, min_count: u32, topk: Option<usize>, classes: bool) -> Self {
+        let mut words: Vec<(String, u32)> = counts
+            .into_iter()
+            .filter(|&(_, count)| count >= min_count)
+            .collect();
+        if let Some(topk) = topk {
+            words.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+            words.truncate(topk);
+        }
+
+        Unkifier {
+            vocabulary: words.into_iter().map(|(word, _)| word).collect(),
+            classes,
+        }
+    }
+
+    /// `token` if it is in the vocabulary, its unknown token otherwise.
+    pub fn replace<'t>(&self, token: &'t str) -> &'t str {
+        if self.vocabulary.contains(token) {
+            token
+        }
+        else if self.classes {
+            unk_class(token)
+        }
+        else {
+            UNK
+        }
+    }
+}
+
+
+
+/// Number of tokens written and replaced.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UnkifyStats {
+    pub tokens: u64,
+    pub unknown: u64,
+}
+
+
+
+/// Buffer - While - for-loop writing the tokens of each line through the shared tokenizer pipeline
+/// with their unknown tokens, separated by a space.
+pub fn unkify<W: Write>(filename: &Option<String>, pipeline: &Pipeline, unkifier: &Unkifier, mut out: W) -> io::Result<(UnkifyStats, PipelineStats)> {
+    let mut stats = UnkifyStats::default();
+    let mut pipeline_stats = PipelineStats::default();
+
+    let mut rdr = try_get_reader(filename)?;
+    let mut line = String::with_capacity(1024);
+    while rdr.read_line(&mut line)? > 0 {
+        let tokens = pipeline.tokens(&line, &mut pipeline_stats);
+        let words: Vec<&str> = tokens.iter().map(|token| unkifier.replace(token)).collect();
+        stats.tokens += words.len() as u64;
+        stats.unknown += words
+            .iter()
+            .zip(&tokens)
+            .filter(|(word, token)| **word != token.as_ref())
+            .count() as u64;
+        writeln!(out, "{}", words.join(" "))?;
+        line.clear();
+    }
+    out.flush()?;
+
+    Ok((stats, pipeline_stats))
+}
@@ -0,0 +1,65 @@
+// vim:nowrap:
+
+// Reading back vocabularies written with --show_counts.
+
+use std::collections::HashSet;
+use std::io::{
+    BufRead,
+    self,
+};
+
+use crate::{
+    try_get_reader,
+    Counts,
+};
+
+
+
+/// Read a `token\tcount` vocabulary.
+/// Lines without a count, as written without --show_counts, count as 1.
+pub fn read_vocabulary(filename: &Option<String>) -> io::Result<Counts> {
+    let mut counts = Counts::new();
+
+    for (i, line) in try_get_reader(filename)?.lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+        let (token, count) = match line.rsplit_once('\t') {
+            Some((token, count)) => {
+                let count = count.trim().parse::<u32>().map_err(|e| io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: invalid count {:?}: {}", i + 1, count, e)))?;
+                (token, count)
+            }
+            None => (line, 1u32),
+        };
+        *counts.entry(token.to_owned()).or_insert(0u32) += count;
+    }
+
+    Ok(counts)
+}
+
+
+
+/// Total number of tokens of a vocabulary.
+pub fn total(counts: &Counts) -> u64 {
+    counts.values().map(|&count| count as u64).sum()
+}
+
+
+
+/// Counts of the tokens of `vocabulary`, 0 when unseen, the counts of the other tokens are added
+/// to `unk`.
+pub fn restrict(counts: &Counts, vocabulary: &HashSet<&String>, unk: &str) -> Counts {
+    let mut restricted: Counts = vocabulary
+        .iter()
+        .map(|&token| (token.clone(), 0u32))
+        .collect();
+    for (token, &count) in counts {
+        let token = if vocabulary.contains(token) { token.as_str() } else { unk };
+        *restricted.entry(token.to_owned()).or_insert(0u32) += count;
+    }
+    restricted
+}